@@ -0,0 +1,56 @@
+/// How much of a file is looked at to tell whether it is binary.
+pub(crate) const SNIFF_LEN: usize = 8192;
+
+/// Signatures at the start of common binary formats, with a description of each.
+const MAGIC_NUMBERS: &[(&[u8], &str)] = &[
+    (b"\0asm", "WebAssembly module"),
+    (b"SQLite format 3\0", "SQLite database"),
+    (b"\x7fELF", "ELF executable"),
+    (b"\xcf\xfa\xed\xfe", "Mach-O executable"),
+    (b"\xce\xfa\xed\xfe", "Mach-O executable"),
+    (b"\xca\xfe\xba\xbe", "Java class or universal Mach-O binary"),
+    (b"%PDF-", "PDF document"),
+    (b"PK\x03\x04", "zip archive"),
+    (b"\x1f\x8b", "gzip archive"),
+    (b"\x28\xb5\x2f\xfd", "zstd archive"),
+    (b"\xfd7zXZ\0", "xz archive"),
+    (b"7z\xbc\xaf\x27\x1c", "7z archive"),
+    (b"\x89PNG\r\n\x1a\n", "PNG image"),
+    (b"\xff\xd8\xff", "JPEG image"),
+    (b"GIF87a", "GIF image"),
+    (b"GIF89a", "GIF image"),
+    (b"OggS", "Ogg media"),
+];
+
+/// Returns why `bytes` look like the contents of a binary file rather than text, judging
+/// by their first few KB.
+pub(crate) fn sniff(bytes: &[u8]) -> Option<&'static str> {
+    if let Some((_, kind)) = MAGIC_NUMBERS
+        .iter()
+        .find(|(magic, _)| bytes.starts_with(magic))
+    {
+        return Some(kind);
+    }
+
+    // Text in UTF-16 is full of NUL bytes
+    let utf16 = bytes.starts_with(b"\xff\xfe") || bytes.starts_with(b"\xfe\xff");
+    if !utf16 && bytes[..bytes.len().min(SNIFF_LEN)].contains(&0) {
+        return Some("contains NUL bytes");
+    }
+    None
+}
+
+/// Whether text decoded from a file not in UTF-8 reads as text, rather than binary data
+/// that happens to decode: at most 1 in 100 of its first characters may be control
+/// characters other than whitespace and escapes.
+pub(crate) fn looks_like_text(text: &str) -> bool {
+    let mut chars = 0;
+    let mut controls = 0;
+    for c in text.chars().take(SNIFF_LEN) {
+        chars += 1;
+        if c.is_control() && !c.is_whitespace() && c != '\x1b' {
+            controls += 1;
+        }
+    }
+    controls * 100 <= chars
+}
@@ -0,0 +1,199 @@
+use crate::{
+    error::{Error, Result},
+    file::PackedFile,
+    format::{FormatterFactory, OmittedFile, PackInfo},
+    pack::section_tokens,
+    tokens::Tokenizer,
+};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use std::{fmt, io, str::FromStr};
+
+/// How files are ranked when they do not all fit in the token budget.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BudgetStrategy {
+    /// Cheapest files first, which keeps as many files as possible.
+    #[default]
+    Smallest,
+    /// Files closest to the packed directory first.
+    Shallowest,
+    /// Files in the order they would be packed.
+    Order,
+}
+
+impl FromStr for BudgetStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "smallest" => Ok(BudgetStrategy::Smallest),
+            "shallowest" => Ok(BudgetStrategy::Shallowest),
+            "order" => Ok(BudgetStrategy::Order),
+            _ => Err(format!(
+                "unknown budget strategy '{}' (expected one of: smallest, shallowest, order)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for BudgetStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            BudgetStrategy::Smallest => "smallest",
+            BudgetStrategy::Shallowest => "shallowest",
+            BudgetStrategy::Order => "order",
+        })
+    }
+}
+
+/// Compiles globs matched against paths relative to the packed directory.
+pub(crate) fn path_globs<I, S>(globs: I) -> Result<GlobSet>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let mut builder = GlobSetBuilder::new();
+    let mut patterns = Vec::new();
+    for glob in globs {
+        let pattern = glob.as_ref();
+        // `*` stays within a directory, so `src/*` and `src/**` mean different things
+        let glob = GlobBuilder::new(pattern)
+            .literal_separator(true)
+            .build()
+            .map_err(|source| Error::InvalidGlob {
+                pattern: pattern.to_string(),
+                source,
+            })?;
+        builder.add(glob);
+        patterns.push(pattern.to_string());
+    }
+    builder.build().map_err(|source| Error::InvalidGlob {
+        pattern: patterns.join(", "),
+        source,
+    })
+}
+
+/// Limits a pack to a number of tokens, keeping the files with the highest priority.
+///
+/// Files matching one of the priority globs always rank first (earlier globs first); the
+/// strategy ranks the remaining ties.
+#[derive(Debug, Clone)]
+pub struct TokenBudget {
+    max_tokens: usize,
+    strategy: BudgetStrategy,
+    priority: GlobSet,
+}
+
+impl TokenBudget {
+    pub fn new(max_tokens: usize) -> Self {
+        Self {
+            max_tokens,
+            strategy: BudgetStrategy::default(),
+            priority: GlobSet::empty(),
+        }
+    }
+
+    pub fn with_strategy(mut self, strategy: BudgetStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Ranks files matching these globs (relative to the packed directory) first.
+    pub fn with_priority<I, S>(mut self, globs: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.priority = path_globs(globs)?;
+        Ok(self)
+    }
+
+    pub fn max_tokens(&self) -> usize {
+        self.max_tokens
+    }
+
+    /// Keeps the files that fit in the budget, in their original order, and records the
+    /// others in `info.omitted`.
+    pub(crate) fn fit(
+        &self,
+        files: Vec<PackedFile>,
+        formatters: &FormatterFactory,
+        tokenizer: &dyn Tokenizer,
+        info: &mut PackInfo,
+    ) -> io::Result<Vec<PackedFile>> {
+        let mut formatter = formatters.formatter();
+        let mut section = Vec::new();
+        let costs = files
+            .iter()
+            .map(|file| {
+                section.clear();
+                formatter.write_file(&mut section, file)?;
+                Ok(section_tokens(
+                    &String::from_utf8_lossy(&section),
+                    file,
+                    tokenizer,
+                ))
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        let mut measure = |write: &mut dyn FnMut(&mut Vec<u8>) -> io::Result<()>| {
+            section.clear();
+            write(&mut section)?;
+            Ok::<_, io::Error>(tokenizer.count(&String::from_utf8_lossy(&section)))
+        };
+
+        let mut order: Vec<usize> = (0..files.len()).collect();
+        order.sort_by_key(|&i| {
+            let rank = self
+                .priority
+                .matches(&files[i].path)
+                .into_iter()
+                .min()
+                .unwrap_or(usize::MAX);
+            let key = match self.strategy {
+                BudgetStrategy::Smallest => costs[i],
+                BudgetStrategy::Shallowest => files[i].path.components().count(),
+                BudgetStrategy::Order => 0,
+            };
+            (rank, key, i)
+        });
+
+        info.max_tokens = Some(self.max_tokens);
+        info.omitted.clear();
+
+        // The preamble and trailer grow as files are omitted, so reserve room for them and
+        // try again until the reservation covers them.
+        let mut keep = vec![false; files.len()];
+        let mut reserved = 0;
+        loop {
+            let available = self.max_tokens.saturating_sub(reserved);
+            let mut used = 0;
+            for &i in &order {
+                keep[i] = used + costs[i] <= available;
+                if keep[i] {
+                    used += costs[i];
+                }
+            }
+
+            info.omitted = (0..files.len())
+                .filter(|&i| !keep[i])
+                .map(|i| OmittedFile {
+                    path: files[i].path.clone(),
+                    tokens: costs[i],
+                })
+                .collect();
+
+            let overhead = measure(&mut |w| formatter.write_header(w, info))?
+                + measure(&mut |w| formatter.write_footer(w, info))?;
+            if overhead <= reserved {
+                break;
+            }
+            reserved = overhead;
+        }
+
+        Ok(files
+            .into_iter()
+            .zip(keep)
+            .filter_map(|(file, keep)| keep.then_some(file))
+            .collect())
+    }
+}
@@ -0,0 +1,300 @@
+use crate::{
+    budget::{path_globs, TokenBudget},
+    constants::DEFAULT_EXCLUSIONS,
+    encoding,
+    error::Error,
+    file::{LineNumbers, PackedFile},
+    format::{Format, FormatterFactory, OutputFormat},
+    git::GitSelection,
+    sort::SortOrder,
+    split::SplitLimit,
+    tokens::TokenizerKind,
+    tree::TreeMode,
+    truncate::{FileSizeLimit, TruncateStrategy},
+    DirectoryProcessor, FilterExpr, STDOUT,
+};
+use globset::{GlobBuilder, GlobMatcher};
+use std::sync::Arc;
+
+/// Configures a [`DirectoryProcessor`].
+///
+/// Every option has a default, so only the ones that matter need to be set:
+///
+/// ```no_run
+/// use codepack::{DirectoryProcessor, Format};
+///
+/// let processor = DirectoryProcessor::builder()
+///     .extensions(["rs", "toml"])
+///     .excluded_files(["*.lock"])
+///     .format(Format::Markdown)
+///     .build()?;
+/// # Ok::<(), codepack::Error>(())
+/// ```
+#[derive(Debug, Default)]
+pub struct DirectoryProcessorBuilder {
+    extensions: Vec<String>,
+    excluded_files: Vec<String>,
+    suppress_prompt: bool,
+    output: Option<String>,
+    force: bool,
+    filters: Vec<FilterExpr>,
+    format: Format,
+    formatter: Option<FormatterFactory>,
+    tokenizer: Option<TokenizerKind>,
+    budget: Option<TokenBudget>,
+    split: Option<SplitLimit>,
+    sort: SortOrder,
+    sort_first: Vec<String>,
+    tree: TreeMode,
+    toc: bool,
+    line_numbers: Option<LineNumbers>,
+    force_text: Vec<String>,
+    strict: bool,
+    encodings: Vec<(String, String)>,
+    max_file_size: Option<(FileSizeLimit, TruncateStrategy)>,
+    git: Vec<GitSelection>,
+}
+
+impl DirectoryProcessorBuilder {
+    /// Only packs files with these extensions (all files by default).
+    pub fn extensions<I, S>(mut self, extensions: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.extensions
+            .extend(extensions.into_iter().map(Into::into));
+        self
+    }
+
+    /// Leaves out files and directories matching these globs, on top of the default
+    /// exclusions.
+    pub fn excluded_files<I, S>(mut self, patterns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.excluded_files
+            .extend(patterns.into_iter().map(Into::into));
+        self
+    }
+
+    /// Leaves the description of the pack layout out of the pack.
+    pub fn suppress_prompt(mut self, suppress_prompt: bool) -> Self {
+        self.suppress_prompt = suppress_prompt;
+        self
+    }
+
+    /// Sets the file `run` writes to, or [`STDOUT`](crate::STDOUT) (`code_pack.txt` by
+    /// default, with the extension of the format).
+    pub fn output(mut self, output: impl Into<String>) -> Self {
+        self.output = Some(output.into());
+        self
+    }
+
+    /// Overwrites the output file without asking.
+    pub fn force(mut self, force: bool) -> Self {
+        self.force = force;
+        self
+    }
+
+    /// Only packs files matching one of these filters, which may be single [`Filter`]s or
+    /// expressions combining them.
+    ///
+    /// [`Filter`]: crate::Filter
+    pub fn filters<I, F>(mut self, filters: I) -> Self
+    where
+        I: IntoIterator<Item = F>,
+        F: Into<FilterExpr>,
+    {
+        self.filters.extend(filters.into_iter().map(Into::into));
+        self
+    }
+
+    /// Sets the layout of the pack (plain text by default).
+    pub fn format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Lays the pack out with a custom format instead, calling `new_formatter` with the files
+    /// of every pack or part (none when only measuring one) for a fresh formatter.
+    ///
+    /// The extension of the default output file still follows `format`, and packs in a custom
+    /// format cannot be unpacked.
+    pub fn formatter<F>(mut self, new_formatter: F) -> Self
+    where
+        F: Fn(&[PackedFile]) -> Box<dyn OutputFormat> + Send + Sync + 'static,
+    {
+        self.formatter = Some(FormatterFactory::Custom(Arc::new(new_formatter)));
+        self
+    }
+
+    /// Sets how tokens are counted. By default they are estimated, unless a token budget or
+    /// a split by tokens needs exact counts, which use o200k.
+    pub fn tokenizer(mut self, tokenizer: TokenizerKind) -> Self {
+        self.tokenizer = Some(tokenizer);
+        self
+    }
+
+    /// Leaves out files, by priority, until the pack fits in the budget.
+    pub fn token_budget(mut self, budget: TokenBudget) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    /// Writes the pack as numbered parts (`name.part1.txt`, ...) that each fit in `limit`.
+    pub fn split(mut self, limit: SplitLimit) -> Self {
+        self.split = Some(limit);
+        self
+    }
+
+    /// Sets the order files are written in (by path by default).
+    pub fn sort(mut self, order: SortOrder) -> Self {
+        self.sort = order;
+        self
+    }
+
+    /// Writes files matching these globs (relative to the packed directory) before all
+    /// others, earlier globs first, e.g. `["README*", "Cargo.toml"]`.
+    pub fn sort_first<I, S>(mut self, globs: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.sort_first.extend(globs.into_iter().map(Into::into));
+        self
+    }
+
+    /// Shows a directory tree of the packed files, and optionally of the files left out,
+    /// after the preamble (off by default).
+    pub fn tree(mut self, tree: TreeMode) -> Self {
+        self.tree = tree;
+        self
+    }
+
+    /// Lists every file with the line of the pack its contents start on, its line count and
+    /// size after the preamble (off by default; JSON formats already carry this metadata).
+    pub fn toc(mut self, toc: bool) -> Self {
+        self.toc = toc;
+        self
+    }
+
+    /// Prefixes every line of each file with its line number in the file (off by default).
+    pub fn line_numbers(mut self, line_numbers: LineNumbers) -> Self {
+        self.line_numbers = Some(line_numbers);
+        self
+    }
+
+    /// Reads files matching these globs (by name, or by path relative to the packed
+    /// directory) as text even if they look binary or have the extension of a binary format.
+    pub fn force_text<I, S>(mut self, globs: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.force_text.extend(globs.into_iter().map(Into::into));
+        self
+    }
+
+    /// Fails on the first file that cannot be read, before writing anything, instead of
+    /// leaving it out and listing it in the summary (off by default).
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Reads files matching `glob` (by name, or by path relative to the packed directory) in
+    /// the encoding named by `label`, such as `windows-1252` or `utf-16le`, instead of
+    /// detecting it. Earlier globs win.
+    pub fn encoding(mut self, glob: impl Into<String>, label: impl Into<String>) -> Self {
+        self.encodings.push((glob.into(), label.into()));
+        self
+    }
+
+    /// Cuts files larger than `limit` down with `strategy`, e.g. leaves them out or keeps
+    /// their first and last lines (no limit by default).
+    pub fn max_file_size(mut self, limit: FileSizeLimit, strategy: TruncateStrategy) -> Self {
+        self.max_file_size = Some((limit, strategy));
+        self
+    }
+
+    /// Packs only the files `selection` picks from the git repository holding the directory,
+    /// which is read directly, without the `git` executable. With several selections, a file
+    /// picked by any of them is packed.
+    pub fn git(mut self, selection: GitSelection) -> Self {
+        self.git.push(selection);
+        self
+    }
+
+    /// Checks the options and builds the processor.
+    pub fn build(self) -> Result<DirectoryProcessor, Error> {
+        if self.split.is_some() && self.output.as_deref() == Some(STDOUT) {
+            return Err(Error::InvalidOptions(
+                "a pack written to stdout cannot be split into parts".to_string(),
+            ));
+        }
+
+        for selection in &self.git {
+            selection.validate()?;
+        }
+
+        let excluded_matchers = DEFAULT_EXCLUSIONS
+            .iter()
+            .map(|s| s.to_string())
+            .chain(self.excluded_files)
+            .map(|pattern| match GlobBuilder::new(&pattern).build() {
+                Ok(glob) => Ok(glob.compile_matcher()),
+                Err(source) => Err(Error::InvalidGlob { pattern, source }),
+            })
+            .collect::<Result<Vec<GlobMatcher>, Error>>()?;
+
+        let encodings = self
+            .encodings
+            .into_iter()
+            .map(|(pattern, label)| {
+                let encoding = encoding::for_label(&label).ok_or_else(|| {
+                    Error::InvalidOptions(format!("unknown encoding '{}'", label))
+                })?;
+                match GlobBuilder::new(&pattern).literal_separator(true).build() {
+                    Ok(glob) => Ok((glob.compile_matcher(), encoding)),
+                    Err(source) => Err(Error::InvalidGlob { pattern, source }),
+                }
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        Ok(DirectoryProcessor {
+            extensions: Arc::new(self.extensions),
+            excluded_matchers: Arc::new(excluded_matchers),
+            suppress_prompt: self.suppress_prompt,
+            output: self
+                .output
+                .unwrap_or_else(|| format!("code_pack.{}", self.format.extension())),
+            force: self.force,
+            confirmed: false,
+            filters: self.filters,
+            formatters: self
+                .formatter
+                .unwrap_or(FormatterFactory::Builtin(self.format)),
+            tokenizer: self.tokenizer.unwrap_or(
+                if self.budget.is_some() || matches!(self.split, Some(SplitLimit::Tokens(_))) {
+                    TokenizerKind::O200k
+                } else {
+                    TokenizerKind::Estimate
+                },
+            ),
+            budget: self.budget,
+            split: self.split,
+            sort: self.sort,
+            sort_first: path_globs(&self.sort_first)?,
+            tree: self.tree,
+            toc: self.toc,
+            line_numbers: self.line_numbers,
+            force_text: path_globs(&self.force_text)?,
+            strict: self.strict,
+            encodings,
+            max_file_size: self.max_file_size,
+            git: self.git,
+        })
+    }
+}
@@ -1,4 +1,4 @@
-pub const UNSUPPORTED_EXTENSIONS: &'static [&'static str] = &[
+pub const UNSUPPORTED_EXTENSIONS: &[&str] = &[
     "eot", "tiff", "tff", "woff", "woff2", "otf", // Fonts
     "jpg", "png", "gif", "jfif", "webp", "bmp", "ico", "svg", // Images
     "mp4", "mov", "avi", "flv", // Videos
@@ -11,10 +11,28 @@ pub const UNSUPPORTED_EXTENSIONS: &'static [&'static str] = &[
     "mo", "pdf", // Misc
     "lock", "sum", //  Lock files (May not be human-readable)
 ];
-pub const DEFAULT_EXCLUSIONS: &'static [&'static str] = &[
+pub const DEFAULT_EXCLUSIONS: &[&str] = &[
     "*LICENSE*", ".gitignore", ".git/", // Version control
     "build/", "dist/", "out/", "target/", "__pycache__/",  // Build/dependency
     ".vscode/", ".idea/", "*.sublime-project", "*.code-workspace",  // IDE
     "venv/", ".venv/", "env/", "conda-env/", "node_modules/",  // Environments
     "*.env", "*.secret", "*.key", "secrets.yml", "*_rsa", "*.pem"  // Secrets
 ];
+pub const LANGUAGES: &[(&str, &str)] = &[
+    ("rs", "rust"), ("py", "python"), ("pyi", "python"), ("js", "javascript"), ("mjs", "javascript"),
+    ("cjs", "javascript"), ("jsx", "jsx"), ("ts", "typescript"), ("tsx", "tsx"), ("go", "go"),
+    ("java", "java"), ("kt", "kotlin"), ("kts", "kotlin"), ("scala", "scala"), ("swift", "swift"),
+    ("c", "c"), ("h", "c"), ("cc", "cpp"), ("cpp", "cpp"), ("cxx", "cpp"), ("hpp", "cpp"),
+    ("cs", "csharp"), ("rb", "ruby"), ("php", "php"), ("lua", "lua"), ("pl", "perl"), ("r", "r"),
+    ("dart", "dart"), ("ex", "elixir"), ("exs", "elixir"), ("erl", "erlang"), ("hs", "haskell"),
+    ("ml", "ocaml"), ("clj", "clojure"), ("zig", "zig"), ("nim", "nim"), ("jl", "julia"), // Languages
+    ("sh", "bash"), ("bash", "bash"), ("zsh", "zsh"), ("fish", "fish"), ("ps1", "powershell"),
+    ("bat", "batch"), // Shells
+    ("html", "html"), ("htm", "html"), ("css", "css"), ("scss", "scss"), ("sass", "sass"),
+    ("less", "less"), ("vue", "vue"), ("svelte", "svelte"), // Web
+    ("json", "json"), ("toml", "toml"), ("yaml", "yaml"), ("yml", "yaml"), ("xml", "xml"),
+    ("ini", "ini"), ("csv", "csv"), ("sql", "sql"), ("graphql", "graphql"), ("proto", "protobuf"), // Data
+    ("md", "markdown"), ("markdown", "markdown"), ("rst", "rst"), ("tex", "latex"), // Docs
+    ("dockerfile", "dockerfile"), ("mk", "makefile"), ("cmake", "cmake"), ("nix", "nix"),
+    ("tf", "hcl"), ("gradle", "groovy"), // Build
+];
@@ -0,0 +1,114 @@
+use chardetng::EncodingDetector;
+use encoding_rs::{CoderResult, Encoding, UTF_8};
+use std::io::{self, Read};
+
+/// How much of a streamed file is decoded at a time.
+const CHUNK_LEN: usize = 64 * 1024;
+
+/// Decodes the contents of a text file: by its byte order mark if it has one, as UTF-8 if
+/// valid, otherwise in the legacy encoding its bytes suggest. Returns the text with the
+/// encoding it was read in.
+pub(crate) fn decode(bytes: Vec<u8>) -> (String, &'static Encoding) {
+    // UTF-8 files keep their BOM, so they unpack to the same bytes
+    if let Some((encoding, bom_len)) = Encoding::for_bom(&bytes) {
+        if encoding != UTF_8 {
+            let (text, _) = encoding.decode_without_bom_handling(&bytes[bom_len..]);
+            return (text.into_owned(), encoding);
+        }
+    }
+
+    match String::from_utf8(bytes) {
+        Ok(text) => (text, UTF_8),
+        Err(err) => {
+            let bytes = err.into_bytes();
+            let mut detector = EncodingDetector::new();
+            detector.feed(&bytes, true);
+            let encoding = detector.guess(None, true);
+            let (text, _) = encoding.decode_without_bom_handling(&bytes);
+            (text.into_owned(), encoding)
+        }
+    }
+}
+
+/// Guesses the encoding of a file from its first bytes, the way [`decode`] does from all of
+/// them.
+pub(crate) fn detect(prefix: &[u8]) -> &'static Encoding {
+    if let Some((encoding, _)) = Encoding::for_bom(prefix) {
+        return encoding;
+    }
+    match std::str::from_utf8(prefix) {
+        Ok(_) => UTF_8,
+        // Cut off in the middle of a character
+        Err(err) if err.error_len().is_none() => UTF_8,
+        Err(_) => {
+            let mut detector = EncodingDetector::new();
+            detector.feed(prefix, false);
+            detector.guess(None, true)
+        }
+    }
+}
+
+/// Decodes text in `encoding` from `reader` a few lines at a time, passing them to `f` until
+/// it returns `false`. Bytes not valid in the encoding are replaced, and lines longer than
+/// a chunk are passed in pieces.
+pub(crate) fn read_lines(
+    mut reader: impl Read,
+    encoding: &'static Encoding,
+    mut f: impl FnMut(&str) -> bool,
+) -> io::Result<()> {
+    let mut decoder = encoding.new_decoder_with_bom_removal();
+    let mut bytes = vec![0; CHUNK_LEN];
+    let mut text = String::new();
+    loop {
+        let read = match reader.read(&mut bytes) {
+            Ok(read) => read,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+        let last = read == 0;
+
+        let mut input = &bytes[..read];
+        loop {
+            text.reserve(
+                decoder
+                    .max_utf8_buffer_length(input.len())
+                    .unwrap_or(CHUNK_LEN),
+            );
+            let (result, consumed, _) = decoder.decode_to_string(input, &mut text, last);
+            input = &input[consumed..];
+            if result == CoderResult::InputEmpty {
+                break;
+            }
+        }
+
+        // Keep a line cut off by the chunk for the next one
+        let end = match text.rfind('\n') {
+            _ if last => text.len(),
+            Some(newline) => newline + 1,
+            None if text.len() >= CHUNK_LEN => text.len(),
+            None => 0,
+        };
+        if end > 0 {
+            if !f(&text[..end]) {
+                return Ok(());
+            }
+            text.drain(..end);
+        }
+        if last {
+            return Ok(());
+        }
+    }
+}
+
+/// Decodes the contents of a file known to be in `encoding`, returning `None` if they are
+/// not valid in it. A byte order mark is removed.
+pub(crate) fn decode_as(bytes: &[u8], encoding: &'static Encoding) -> Option<String> {
+    let (text, had_errors) = encoding.decode_with_bom_removal(bytes);
+    (!had_errors).then(|| text.into_owned())
+}
+
+/// Looks up an encoding by one of its WHATWG labels, such as `latin1`, `windows-1252` or
+/// `utf-16le`.
+pub(crate) fn for_label(label: &str) -> Option<&'static Encoding> {
+    Encoding::for_label(label.trim().as_bytes())
+}
@@ -0,0 +1,123 @@
+use crate::filter::EXPECTED_KEYS;
+use std::{
+    fmt, io,
+    path::{Path, PathBuf},
+};
+
+/// Errors returned by the library.
+#[derive(Debug)]
+pub enum Error {
+    /// A glob pattern that could not be parsed.
+    InvalidGlob {
+        pattern: String,
+        source: globset::Error,
+    },
+    /// A filter that could not be parsed.
+    InvalidFilter(String),
+    /// A filter whose value could not be parsed, such as the size of `size<10q`.
+    InvalidFilterValue { filter: String, reason: String },
+    /// A regex pattern that could not be parsed.
+    InvalidRegex {
+        pattern: String,
+        source: regex::Error,
+    },
+    /// A filter expression that could not be parsed.
+    FilterSyntax {
+        expression: String,
+        /// Of the character the error was found at, starting at 1.
+        column: usize,
+        reason: String,
+    },
+    /// Options that cannot be used together.
+    InvalidOptions(String),
+    /// The output file holds data that is not a previous pack, and overwriting was not forced.
+    OutputExists(PathBuf),
+    /// The user declined to overwrite the output file.
+    Cancelled,
+    /// Reading or writing a file failed.
+    Io { path: PathBuf, source: io::Error },
+    /// Writing the pack to a writer rather than a file failed, such as the one given to
+    /// `write_to`.
+    Write(io::Error),
+    /// A file to pack is not valid UTF-8.
+    NonUtf8(PathBuf),
+    /// A file to pack is not valid in the encoding it was declared to be in.
+    InvalidEncoding {
+        path: PathBuf,
+        encoding: &'static str,
+    },
+    /// Git could not list the files to pack, e.g. outside a repository or for an unknown
+    /// revision.
+    Git(String),
+    /// A pack that could not be parsed.
+    InvalidPack(String),
+    /// A path in a pack that would be written outside the destination.
+    UnsafePath(PathBuf),
+}
+
+/// The result type of the library.
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+impl Error {
+    /// Wraps an I/O error with the path it happened on, for use with `map_err`.
+    pub(crate) fn io(path: impl AsRef<Path>) -> impl FnOnce(io::Error) -> Error {
+        let path = path.as_ref().to_owned();
+        move |source| Error::Io { path, source }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidGlob { pattern, .. } => write!(f, "invalid glob '{}'", pattern),
+            Error::InvalidFilter(filter) => {
+                write!(f, "invalid filter '{}' ({})", filter, EXPECTED_KEYS)
+            }
+            Error::InvalidFilterValue { filter, reason } => {
+                write!(f, "invalid filter '{}': {}", filter, reason)
+            }
+            Error::InvalidRegex { pattern, .. } => write!(f, "invalid regex '{}'", pattern),
+            Error::FilterSyntax {
+                expression,
+                column,
+                reason,
+            } => write!(
+                f,
+                "invalid filter expression '{}' at column {}: {}",
+                expression, column, reason
+            ),
+            Error::InvalidOptions(reason) => f.write_str(reason),
+            Error::OutputExists(path) => write!(
+                f,
+                "output file {} already exists and contains data (use --force to overwrite)",
+                path.display()
+            ),
+            Error::Cancelled => f.write_str("operation cancelled"),
+            Error::Io { path, .. } => write!(f, "I/O error on {}", path.display()),
+            Error::Write(_) => f.write_str("I/O error writing the pack"),
+            Error::NonUtf8(path) => write!(f, "{} is not valid UTF-8", path.display()),
+            Error::InvalidEncoding { path, encoding } => {
+                write!(f, "{} is not valid {}", path.display(), encoding)
+            }
+            Error::Git(reason) => write!(f, "git: {}", reason),
+            Error::InvalidPack(reason) => write!(f, "invalid pack: {}", reason),
+            Error::UnsafePath(path) => write!(
+                f,
+                "refusing to write outside the destination: {}",
+                path.display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::InvalidGlob { source, .. } => Some(source),
+            Error::InvalidRegex { source, .. } => Some(source),
+            Error::Io { source, .. } => Some(source),
+            Error::Write(source) => Some(source),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,208 @@
+use crate::format::language_for;
+use serde::{Deserialize, Serialize};
+use std::{fmt, fmt::Write, path::PathBuf, str::FromStr};
+
+/// Follows the path in the label of a truncated file.
+pub(crate) const TRUNCATED_MARKER: &str = " (truncated)";
+
+/// A file included in the pack, along with the metadata reported by structured formats.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PackedFile {
+    pub path: PathBuf,
+    /// Size of the file in bytes as it was read, before its content was transcoded, numbered
+    /// or truncated. For a piece of a split file, the size of the lines it holds.
+    pub size: u64,
+    /// Lines of the file as it was read, or of the piece of a split file.
+    pub lines: usize,
+    pub extension: Option<String>,
+    pub language: Option<&'static str>,
+    pub content: String,
+    /// Set when the content is only a piece of a file too large for one part.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chunk: Option<Chunk>,
+    /// Set when the content was cut down to fit `--max-file-size`, so it is not the whole
+    /// file.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+    /// Written after the number starting every line, when the content is numbered.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub number_separator: Option<String>,
+    /// Tokens of the content, counted while the file was read so the pack is not tokenized
+    /// whole in the writer.
+    #[serde(skip)]
+    pub(crate) tokens: Option<usize>,
+}
+
+/// The lines of a file held by one piece of it, when it is split across parts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Chunk {
+    pub first_line: usize,
+    pub last_line: usize,
+    pub total_lines: usize,
+}
+
+impl PackedFile {
+    pub fn new(path: PathBuf, content: String) -> Self {
+        Self {
+            size: content.len() as u64,
+            lines: content.lines().count(),
+            extension: path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .map(str::to_string),
+            language: language_for(&path),
+            path,
+            content,
+            chunk: None,
+            truncated: false,
+            number_separator: None,
+            tokens: None,
+        }
+    }
+
+    /// Returns a piece of this file holding `lines`, which must be consecutive lines
+    /// (with their line endings) starting at `first_line`.
+    pub fn chunk(&self, first_line: usize, lines: &[&str]) -> Self {
+        let piece = Self::new(self.path.clone(), lines.concat());
+        // Measure the lines held without the numbers added to them
+        let size = match &self.number_separator {
+            Some(separator) if !self.truncated => LineNumbers {
+                width: None,
+                separator: separator.clone(),
+            }
+            .strip(&piece.content)
+            .map_or(piece.size, |stripped| stripped.len() as u64),
+            _ => piece.size,
+        };
+        Self {
+            size,
+            chunk: Some(Chunk {
+                first_line,
+                last_line: first_line + lines.len().max(1) - 1,
+                total_lines: self.content.lines().count(),
+            }),
+            truncated: self.truncated,
+            number_separator: self.number_separator.clone(),
+            ..piece
+        }
+    }
+
+    /// The path, followed by a marker if the content is truncated and by the lines held if
+    /// this is a piece of a larger file.
+    pub fn label(&self) -> String {
+        let mut label = self.path.display().to_string();
+        if self.truncated {
+            label.push_str(TRUNCATED_MARKER);
+        }
+        if let Some(chunk) = &self.chunk {
+            // Writing to a String cannot fail
+            let _ = write!(label, " ({})", chunk);
+        }
+        label
+    }
+}
+
+/// How the lines of each file are numbered, so they can be referred to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineNumbers {
+    /// Numbers are right-aligned to this many columns, or to the widest number of the file
+    /// if unset.
+    pub width: Option<usize>,
+    /// Written between a number and its line.
+    pub separator: String,
+}
+
+impl Default for LineNumbers {
+    fn default() -> Self {
+        Self {
+            width: None,
+            separator: " | ".to_string(),
+        }
+    }
+}
+
+impl LineNumbers {
+    /// Prefixes every line of `content` with its number, starting at 1. Empty lines get the
+    /// separator without its trailing whitespace.
+    pub fn apply(&self, content: &str) -> String {
+        self.apply_from(content, 1, content.lines().count())
+    }
+
+    /// Numbers `content`, a piece of a file of `total_lines` lines starting at `first_line`.
+    pub(crate) fn apply_from(
+        &self,
+        content: &str,
+        first_line: usize,
+        total_lines: usize,
+    ) -> String {
+        let width = self
+            .width
+            .unwrap_or_else(|| total_lines.max(1).to_string().len());
+        let blank = self.separator.trim_end();
+
+        let lines = content.lines().count();
+        let mut numbered = String::with_capacity(content.len() + lines * (width + 3));
+        for (i, line) in content.split_inclusive('\n').enumerate() {
+            let text = line.trim_end_matches(['\n', '\r']);
+            let separator = if text.is_empty() {
+                blank
+            } else {
+                &self.separator
+            };
+            // Writing to a String cannot fail
+            let _ = write!(numbered, "{:>width$}{}{}", first_line + i, separator, line);
+        }
+        numbered
+    }
+
+    /// Removes the numbers `apply` added to `content`, or returns the line (starting at 1)
+    /// that is not numbered.
+    pub(crate) fn strip(&self, content: &str) -> Result<String, usize> {
+        let blank = self.separator.trim_end();
+
+        let mut stripped = String::with_capacity(content.len());
+        for (i, line) in content.split_inclusive('\n').enumerate() {
+            let rest = line.trim_start_matches(' ');
+            let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+            let rest = &rest[digits..];
+            let text = match rest.strip_prefix(self.separator.as_str()) {
+                Some(text) if digits > 0 => text,
+                _ if digits > 0 && rest.trim_end_matches(['\n', '\r']) == blank => {
+                    &rest[blank.len()..]
+                }
+                _ => return Err(i + 1),
+            };
+            stripped.push_str(text);
+        }
+        Ok(stripped)
+    }
+}
+
+impl fmt::Display for Chunk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "lines {}-{} of {}",
+            self.first_line, self.last_line, self.total_lines
+        )
+    }
+}
+
+impl FromStr for Chunk {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid line range '{}'", s);
+        let (range, total) = s
+            .strip_prefix("lines ")
+            .and_then(|rest| rest.split_once(" of "))
+            .ok_or_else(invalid)?;
+        let (first, last) = range.split_once('-').ok_or_else(invalid)?;
+
+        Ok(Chunk {
+            first_line: first.parse().map_err(|_| invalid())?,
+            last_line: last.parse().map_err(|_| invalid())?,
+            total_lines: total.parse().map_err(|_| invalid())?,
+        })
+    }
+}
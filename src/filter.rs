@@ -0,0 +1,763 @@
+use crate::{
+    error::{Error, Result},
+    metadata::{Comparison, Timestamp},
+    truncate::FileSizeLimit,
+};
+use globset::{GlobBuilder, GlobMatcher};
+use regex::{Regex, RegexBuilder};
+use std::{
+    cell::OnceCell,
+    fmt,
+    fs::{self, Metadata},
+    path::Path,
+    ptr,
+    str::FromStr,
+    time::SystemTime,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Filter {
+    FileName(String),        // Matches file name
+    PathContains(String),    // Matches a substring in the path
+    ContentContains(String), // Matches a substring in the file content
+    /// Matches a part of the file with a regex, a glob or a case-insensitive substring.
+    Match {
+        field: Field,
+        pattern: Pattern,
+    },
+    /// Compares the size of the file on disk, in bytes.
+    Size(Comparison, u64),
+    /// Compares the number of lines of the file.
+    Lines(Comparison, usize),
+    /// Compares when the file was last modified.
+    Modified(Comparison, Timestamp),
+}
+
+/// The part of a file a [`Filter::Match`] looks at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    FileName,
+    /// Substrings are found in the path as walked, regexes and globs match the path relative
+    /// to the packed directory.
+    Path,
+    Content,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatternKind {
+    Substring,
+    Regex,
+    Glob,
+}
+
+/// A compiled regex, glob or substring.
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    kind: PatternKind,
+    source: String,
+    ignore_case: bool,
+    matcher: Matcher,
+}
+
+#[derive(Debug, Clone)]
+enum Matcher {
+    Regex(Regex),
+    Glob(GlobMatcher),
+}
+
+/// Lists the main filter keys, for error messages.
+pub(crate) const EXPECTED_KEYS: &str = "expected a key such as file.name=, file.glob=, path.contains=, path.glob=, content.contains=, content.regex=, size<, lines> or modified>";
+
+/// The names of the filters comparing a property of the file, followed by an operator.
+const METADATA_KEYS: &[&str] = &["size", "lines", "modified"];
+
+/// The prefix of every filter, with what it matches.
+const FILTER_KEYS: &[(&str, Field, PatternKind, bool)] = &[
+    ("file.name=", Field::FileName, PatternKind::Substring, false),
+    ("file.iname=", Field::FileName, PatternKind::Substring, true),
+    ("file.name~=", Field::FileName, PatternKind::Regex, false),
+    ("file.iname~=", Field::FileName, PatternKind::Regex, true),
+    ("file.glob=", Field::FileName, PatternKind::Glob, false),
+    ("file.iglob=", Field::FileName, PatternKind::Glob, true),
+    ("path.contains=", Field::Path, PatternKind::Substring, false),
+    ("path.icontains=", Field::Path, PatternKind::Substring, true),
+    ("path.regex=", Field::Path, PatternKind::Regex, false),
+    ("path.iregex=", Field::Path, PatternKind::Regex, true),
+    ("path.glob=", Field::Path, PatternKind::Glob, false),
+    ("path.iglob=", Field::Path, PatternKind::Glob, true),
+    (
+        "content.contains=",
+        Field::Content,
+        PatternKind::Substring,
+        false,
+    ),
+    (
+        "content.icontains=",
+        Field::Content,
+        PatternKind::Substring,
+        true,
+    ),
+    ("content.regex=", Field::Content, PatternKind::Regex, false),
+    ("content.iregex=", Field::Content, PatternKind::Regex, true),
+];
+
+impl Pattern {
+    /// Compiles `source` as a pattern of `kind`. Content regexes are multi-line, so `^` and
+    /// `$` match at the start and end of every line.
+    pub fn new(kind: PatternKind, source: &str, ignore_case: bool) -> Result<Self> {
+        let regex = |pattern: &str| {
+            RegexBuilder::new(pattern)
+                .case_insensitive(ignore_case)
+                .multi_line(true)
+                .build()
+                .map_err(|source| Error::InvalidRegex {
+                    pattern: pattern.to_string(),
+                    source,
+                })
+        };
+        let matcher = match kind {
+            PatternKind::Substring => Matcher::Regex(regex(&regex::escape(source))?),
+            PatternKind::Regex => Matcher::Regex(regex(source)?),
+            // `*` stays within a directory, like the other path globs
+            PatternKind::Glob => Matcher::Glob(
+                GlobBuilder::new(source)
+                    .literal_separator(true)
+                    .case_insensitive(ignore_case)
+                    .build()
+                    .map_err(|err| Error::InvalidGlob {
+                        pattern: source.to_string(),
+                        source: err,
+                    })?
+                    .compile_matcher(),
+            ),
+        };
+        Ok(Self {
+            kind,
+            source: source.to_string(),
+            ignore_case,
+            matcher,
+        })
+    }
+
+    pub fn kind(&self) -> PatternKind {
+        self.kind
+    }
+
+    /// The pattern as written.
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    pub fn ignores_case(&self) -> bool {
+        self.ignore_case
+    }
+
+    fn is_match(&self, text: &str) -> bool {
+        match &self.matcher {
+            Matcher::Regex(regex) => regex.is_match(text),
+            Matcher::Glob(glob) => glob.is_match(text),
+        }
+    }
+}
+
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        (self.kind, &self.source, self.ignore_case)
+            == (other.kind, &other.source, other.ignore_case)
+    }
+}
+
+impl Eq for Pattern {}
+
+impl FromStr for Filter {
+    type Err = Error;
+
+    /// Parses a filter written as one of the keys below followed by its value, e.g.
+    /// `file.name=main` or `path.glob=src/api/**/*_handler.rs`.
+    ///
+    /// | Matches   | Substring           | Regex             | Glob          |
+    /// |-----------|---------------------|-------------------|---------------|
+    /// | File name | `file.name=`        | `file.name~=`     | `file.glob=`  |
+    /// | Path      | `path.contains=`    | `path.regex=`     | `path.glob=`  |
+    /// | Contents  | `content.contains=` | `content.regex=`  |               |
+    ///
+    /// Each has a case-insensitive variant with an `i` before the last word, e.g.
+    /// `file.iname=`, `path.iglob=` or `content.icontains=`.
+    ///
+    /// The size, line count and modification time of a file are compared with `<`, `<=`,
+    /// `=`, `>=` or `>`, e.g. `size<100k`, `lines>20`, `modified>2026-09-01` or
+    /// `modified<7d` (modified less than 7 days ago).
+    fn from_str(s: &str) -> Result<Self> {
+        if let Some((key, op, value)) = METADATA_KEYS.iter().find_map(|key| {
+            let (op, value) = Comparison::split(s.strip_prefix(key)?)?;
+            Some((*key, op, value))
+        }) {
+            let invalid = |reason: String| Error::InvalidFilterValue {
+                filter: s.to_string(),
+                reason,
+            };
+            return match key {
+                "size" => match value.parse::<FileSizeLimit>().map_err(invalid)? {
+                    FileSizeLimit::Bytes(bytes) => Ok(Filter::Size(op, bytes as u64)),
+                    FileSizeLimit::Lines(_) => Err(invalid(
+                        "expected a size such as 100k, use lines to compare lines".to_string(),
+                    )),
+                },
+                "lines" => value
+                    .parse()
+                    .map(|lines| Filter::Lines(op, lines))
+                    .map_err(|_| invalid(format!("invalid line count '{}'", value))),
+                _ => {
+                    let time = value.parse::<Timestamp>().map_err(invalid)?;
+                    if matches!(time, Timestamp::Ago(_)) && op == Comparison::Equal {
+                        return Err(invalid("an age is compared with < or >".to_string()));
+                    }
+                    Ok(Filter::Modified(op, time))
+                }
+            };
+        }
+
+        // Longest first, so `file.name~=` is not read as `file.name=` with a value
+        let Some(&(key, field, kind, ignore_case)) = FILTER_KEYS
+            .iter()
+            .filter(|(key, ..)| s.starts_with(key))
+            .max_by_key(|(key, ..)| key.len())
+        else {
+            return Err(Error::InvalidFilter(s.to_string()));
+        };
+        let value = &s[key.len()..];
+
+        Ok(match (field, kind, ignore_case) {
+            (Field::FileName, PatternKind::Substring, false) => Filter::FileName(value.to_string()),
+            (Field::Path, PatternKind::Substring, false) => Filter::PathContains(value.to_string()),
+            (Field::Content, PatternKind::Substring, false) => {
+                Filter::ContentContains(value.to_string())
+            }
+            _ => Filter::Match {
+                field,
+                pattern: Pattern::new(kind, value, ignore_case)?,
+            },
+        })
+    }
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (field, kind, ignore_case, value) = match self {
+            Filter::Size(op, bytes) => return write!(f, "size{}{}", op, bytes),
+            Filter::Lines(op, lines) => return write!(f, "lines{}{}", op, lines),
+            Filter::Modified(op, time) => return write!(f, "modified{}{}", op, time),
+            Filter::FileName(value) => (Field::FileName, PatternKind::Substring, false, value),
+            Filter::PathContains(value) => (Field::Path, PatternKind::Substring, false, value),
+            Filter::ContentContains(value) => {
+                (Field::Content, PatternKind::Substring, false, value)
+            }
+            Filter::Match { field, pattern } => {
+                (*field, pattern.kind, pattern.ignore_case, &pattern.source)
+            }
+        };
+        let key = FILTER_KEYS
+            .iter()
+            .find(|entry| (entry.1, entry.2, entry.3) == (field, kind, ignore_case))
+            .map_or("", |entry| entry.0);
+        write!(f, "{}{}", key, quote(value))
+    }
+}
+
+/// What is known of a file when filters are evaluated.
+pub(crate) struct Candidate<'a> {
+    /// As found by the walk.
+    path: &'a Path,
+    /// Relative to the packed directory.
+    relative_path: &'a Path,
+    content: Content<'a>,
+    /// Looked up by the first filter that needs it, `None` if that failed.
+    metadata: OnceCell<Option<Metadata>>,
+}
+
+impl<'a> Candidate<'a> {
+    pub fn new(path: &'a Path, relative_path: &'a Path, content: Content<'a>) -> Self {
+        Self {
+            path,
+            relative_path,
+            content,
+            metadata: OnceCell::new(),
+        }
+    }
+
+    fn metadata(&self) -> Option<&Metadata> {
+        self.metadata
+            .get_or_init(|| fs::metadata(self.path).ok())
+            .as_ref()
+    }
+}
+
+/// What is known of the contents of a [`Candidate`].
+#[derive(Clone, Copy)]
+pub(crate) enum Content<'a> {
+    Unread,
+    Text(&'a str),
+    /// Being read a few lines at a time.
+    Scanned(&'a ContentScan<'a>),
+}
+
+/// Tracks which content filters have matched a file read a few lines at a time, so large
+/// files can be matched without holding them whole.
+///
+/// A pattern is only found within one piece of the file, so a match spanning two pieces is
+/// missed.
+pub(crate) struct ContentScan<'f> {
+    /// Every content filter of the expressions, with whether it has matched yet.
+    filters: Vec<(&'f Filter, bool)>,
+    /// Line breaks read so far.
+    line_breaks: usize,
+    /// Whether the text read so far ends in the middle of a line.
+    in_line: bool,
+    finished: bool,
+}
+
+impl<'f> ContentScan<'f> {
+    pub fn new(exprs: &'f [FilterExpr]) -> Self {
+        let mut filters = Vec::new();
+        for expr in exprs {
+            expr.content_filters(&mut filters);
+        }
+        Self {
+            filters: filters.into_iter().map(|filter| (filter, false)).collect(),
+            line_breaks: 0,
+            in_line: false,
+            finished: false,
+        }
+    }
+
+    /// Matches the next piece of the file.
+    pub fn feed(&mut self, text: &str) {
+        for (filter, matched) in &mut self.filters {
+            if !*matched && filter.matches_text(text) {
+                *matched = true;
+            }
+        }
+        if !text.is_empty() {
+            self.line_breaks += text.matches('\n').count();
+            self.in_line = !text.ends_with('\n');
+        }
+    }
+
+    /// Marks the end of the file, after which filters not matched yet never will.
+    pub fn finish(&mut self) {
+        self.finished = true;
+    }
+
+    fn matched(&self, filter: &Filter) -> Option<bool> {
+        let matched = self
+            .filters
+            .iter()
+            .any(|(candidate, matched)| *matched && ptr::eq(*candidate, filter));
+        (matched || self.finished).then_some(matched)
+    }
+
+    fn lines(&self, op: Comparison, bound: usize) -> Option<bool> {
+        let at_least = self.line_breaks + usize::from(self.in_line);
+        if self.finished {
+            Some(op.holds(at_least, bound))
+        } else {
+            op.holds_at_least(at_least, bound)
+        }
+    }
+}
+
+impl Filter {
+    /// Whether the file matches, or `None` if that depends on contents not read yet.
+    pub(crate) fn matches(&self, file: &Candidate) -> Option<bool> {
+        let name = || {
+            file.path
+                .file_name()
+                .map(|name| name.to_string_lossy())
+                .unwrap_or_default()
+        };
+        match self {
+            Filter::FileName(pattern) => Some(name().contains(pattern.as_str())),
+            Filter::PathContains(substring) => {
+                Some(file.path.to_string_lossy().contains(substring.as_str()))
+            }
+            Filter::Size(op, bytes) => Some(
+                file.metadata()
+                    .is_some_and(|metadata| op.holds(metadata.len(), *bytes)),
+            ),
+            Filter::Modified(op, time) => Some(
+                file.metadata()
+                    .and_then(|metadata| metadata.modified().ok())
+                    .is_some_and(|modified| time.compare(*op, modified, SystemTime::now())),
+            ),
+            Filter::Lines(op, lines) => match file.content {
+                Content::Unread => None,
+                Content::Text(text) => Some(op.holds(text.lines().count(), *lines)),
+                Content::Scanned(scan) => scan.lines(*op, *lines),
+            },
+            Filter::ContentContains(_)
+            | Filter::Match {
+                field: Field::Content,
+                ..
+            } => match file.content {
+                Content::Unread => None,
+                Content::Text(text) => Some(self.matches_text(text)),
+                Content::Scanned(scan) => scan.matched(self),
+            },
+            Filter::Match { field, pattern } => match field {
+                Field::FileName => Some(pattern.is_match(&name())),
+                Field::Path if pattern.kind == PatternKind::Substring => {
+                    Some(pattern.is_match(&file.path.to_string_lossy()))
+                }
+                // Globs and regexes see `/` separators on every platform
+                Field::Path => {
+                    Some(pattern.is_match(&file.relative_path.to_string_lossy().replace('\\', "/")))
+                }
+                Field::Content => unreachable!("content filters are matched above"),
+            },
+        }
+    }
+
+    /// Whether a content filter matches `text`.
+    fn matches_text(&self, text: &str) -> bool {
+        match self {
+            Filter::ContentContains(substring) => text.contains(substring.as_str()),
+            Filter::Match { pattern, .. } => pattern.is_match(text),
+            _ => false,
+        }
+    }
+}
+
+/// Filters combined with `AND`, `OR`, `NOT` and parentheses, such as
+/// `(path.contains=src AND NOT file.name=test) OR content.contains=TODO`.
+///
+/// `NOT` binds tightest, then `AND`, then `OR`. Values holding spaces or parentheses are
+/// quoted with `"` or `'`, e.g. `content.contains="fn main()"`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FilterExpr {
+    Filter(Filter),
+    Not(Box<FilterExpr>),
+    And(Vec<FilterExpr>),
+    Or(Vec<FilterExpr>),
+}
+
+impl From<Filter> for FilterExpr {
+    fn from(filter: Filter) -> Self {
+        FilterExpr::Filter(filter)
+    }
+}
+
+impl FilterExpr {
+    /// Whether the file matches, or `None` if that depends on contents not read yet.
+    pub(crate) fn matches(&self, file: &Candidate) -> Option<bool> {
+        match self {
+            FilterExpr::Filter(filter) => filter.matches(file),
+            FilterExpr::Not(expr) => expr.matches(file).map(|matches| !matches),
+            // An unknown operand only decides the result if no other operand does
+            FilterExpr::And(exprs) => {
+                let mut result = Some(true);
+                for expr in exprs {
+                    match expr.matches(file) {
+                        Some(false) => return Some(false),
+                        Some(true) => {}
+                        None => result = None,
+                    }
+                }
+                result
+            }
+            FilterExpr::Or(exprs) => {
+                let mut result = Some(false);
+                for expr in exprs {
+                    match expr.matches(file) {
+                        Some(true) => return Some(true),
+                        Some(false) => {}
+                        None => result = None,
+                    }
+                }
+                result
+            }
+        }
+    }
+
+    /// Collects the filters of the expression that match contents.
+    fn content_filters<'f>(&'f self, filters: &mut Vec<&'f Filter>) {
+        match self {
+            FilterExpr::Filter(
+                filter @ (Filter::ContentContains(_)
+                | Filter::Match {
+                    field: Field::Content,
+                    ..
+                }),
+            ) => filters.push(filter),
+            FilterExpr::Filter(_) => {}
+            FilterExpr::Not(expr) => expr.content_filters(filters),
+            FilterExpr::And(exprs) | FilterExpr::Or(exprs) => {
+                for expr in exprs {
+                    expr.content_filters(filters);
+                }
+            }
+        }
+    }
+}
+
+impl FromStr for FilterExpr {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let parsed = tokenize(s).and_then(|tokens| {
+            let mut parser = Parser {
+                expression: s,
+                tokens: &tokens,
+                next: 0,
+            };
+            let expr = parser.or()?;
+            match parser.peek() {
+                None => Ok(expr),
+                Some(token) => Err(parser.error(token.offset, "expected AND, OR or the end")),
+            }
+        });
+
+        match parsed {
+            Ok(expr) => Ok(expr),
+            // Before expressions, everything after the prefix was the value, spaces and all
+            Err(err) => match s.parse::<Filter>() {
+                Ok(filter) if !s.split_whitespace().any(is_keyword) => {
+                    Ok(FilterExpr::Filter(filter))
+                }
+                _ => Err(err),
+            },
+        }
+    }
+}
+
+impl fmt::Display for FilterExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let join = |f: &mut fmt::Formatter<'_>, exprs: &[FilterExpr], op: &str| {
+            for (i, expr) in exprs.iter().enumerate() {
+                if i > 0 {
+                    write!(f, " {} ", op)?;
+                }
+                match expr {
+                    FilterExpr::And(_) | FilterExpr::Or(_) => write!(f, "({})", expr)?,
+                    _ => write!(f, "{}", expr)?,
+                }
+            }
+            Ok(())
+        };
+        match self {
+            FilterExpr::Filter(filter) => write!(f, "{}", filter),
+            FilterExpr::Not(expr) => match **expr {
+                FilterExpr::And(_) | FilterExpr::Or(_) => write!(f, "NOT ({})", expr),
+                _ => write!(f, "NOT {}", expr),
+            },
+            FilterExpr::And(exprs) => join(f, exprs, "AND"),
+            FilterExpr::Or(exprs) => join(f, exprs, "OR"),
+        }
+    }
+}
+
+/// Quotes a filter value if it would not read back as one word.
+fn quote(value: &str) -> String {
+    if value.is_empty() || value.contains(|c: char| c.is_whitespace() || "()\"'".contains(c)) {
+        format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn is_keyword(word: &str) -> bool {
+    ["AND", "OR", "NOT"]
+        .iter()
+        .any(|keyword| word.eq_ignore_ascii_case(keyword))
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum TokenKind {
+    Open,
+    Close,
+    And,
+    Or,
+    Not,
+    Filter(Filter),
+}
+
+#[derive(Debug)]
+struct Token {
+    kind: TokenKind,
+    /// Byte offset in the expression.
+    offset: usize,
+}
+
+fn syntax_error(expression: &str, offset: usize, reason: impl Into<String>) -> Error {
+    Error::FilterSyntax {
+        expression: expression.to_string(),
+        column: expression[..offset].chars().count() + 1,
+        reason: reason.into(),
+    }
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = s.char_indices().peekable();
+    while let Some(&(offset, c)) = chars.peek() {
+        let kind = match c {
+            _ if c.is_whitespace() => {
+                chars.next();
+                continue;
+            }
+            '(' => {
+                chars.next();
+                TokenKind::Open
+            }
+            ')' => {
+                chars.next();
+                TokenKind::Close
+            }
+            _ => {
+                // A keyword, or a filter whose value may be quoted
+                let mut word = String::new();
+                while let Some(&(_, c)) = chars.peek() {
+                    if c.is_whitespace() || c == '(' || c == ')' {
+                        break;
+                    }
+                    chars.next();
+                    if c == '=' && !word.contains('=') {
+                        word.push(c);
+                        if let Some(&(quote_offset, quote @ ('"' | '\''))) = chars.peek() {
+                            chars.next();
+                            word.push_str(&quoted(s, &mut chars, quote, quote_offset)?);
+                            break;
+                        }
+                    } else {
+                        word.push(c);
+                    }
+                }
+
+                match word.to_ascii_uppercase().as_str() {
+                    "AND" => TokenKind::And,
+                    "OR" => TokenKind::Or,
+                    "NOT" => TokenKind::Not,
+                    _ => match word.parse() {
+                        Ok(filter) => TokenKind::Filter(filter),
+                        Err(Error::InvalidFilter(_)) if !word.contains('=') => {
+                            return Err(syntax_error(
+                                s,
+                                offset,
+                                format!("expected a filter, AND, OR or NOT, found '{}'", word),
+                            ))
+                        }
+                        Err(Error::InvalidFilter(_)) => {
+                            return Err(syntax_error(
+                                s,
+                                offset,
+                                format!("unknown filter '{}' ({})", word, EXPECTED_KEYS),
+                            ))
+                        }
+                        // A regex or glob that does not compile
+                        Err(err) => return Err(err),
+                    },
+                }
+            }
+        };
+        tokens.push(Token { kind, offset });
+    }
+    Ok(tokens)
+}
+
+/// Reads a quoted value up to its closing quote, after the opening one.
+fn quoted(
+    s: &str,
+    chars: &mut std::iter::Peekable<std::str::CharIndices>,
+    quote: char,
+    offset: usize,
+) -> Result<String> {
+    let mut value = String::new();
+    while let Some((_, c)) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some((_, escaped)) => value.push(escaped),
+                None => break,
+            },
+            _ if c == quote => return Ok(value),
+            _ => value.push(c),
+        }
+    }
+    Err(syntax_error(s, offset, "unterminated quote"))
+}
+
+struct Parser<'a> {
+    expression: &'a str,
+    tokens: &'a [Token],
+    next: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.next)
+    }
+
+    fn error(&self, offset: usize, reason: &str) -> Error {
+        syntax_error(self.expression, offset, reason)
+    }
+
+    /// Where a missing operand would have been.
+    fn end(&self) -> usize {
+        self.peek()
+            .map_or(self.expression.len(), |token| token.offset)
+    }
+
+    fn or(&mut self) -> Result<FilterExpr> {
+        let mut exprs = vec![self.and()?];
+        while self.peek().is_some_and(|token| token.kind == TokenKind::Or) {
+            self.next += 1;
+            exprs.push(self.and()?);
+        }
+        Ok(if exprs.len() == 1 {
+            exprs.remove(0)
+        } else {
+            FilterExpr::Or(exprs)
+        })
+    }
+
+    fn and(&mut self) -> Result<FilterExpr> {
+        let mut exprs = vec![self.not()?];
+        while self
+            .peek()
+            .is_some_and(|token| token.kind == TokenKind::And)
+        {
+            self.next += 1;
+            exprs.push(self.not()?);
+        }
+        Ok(if exprs.len() == 1 {
+            exprs.remove(0)
+        } else {
+            FilterExpr::And(exprs)
+        })
+    }
+
+    fn not(&mut self) -> Result<FilterExpr> {
+        let Some(token) = self.tokens.get(self.next) else {
+            return Err(self.error(self.end(), "expected a filter"));
+        };
+        self.next += 1;
+        match &token.kind {
+            TokenKind::Not => Ok(FilterExpr::Not(Box::new(self.not()?))),
+            TokenKind::Open => {
+                let expr = self.or()?;
+                match self.peek() {
+                    Some(Token {
+                        kind: TokenKind::Close,
+                        ..
+                    }) => {
+                        self.next += 1;
+                        Ok(expr)
+                    }
+                    _ => Err(self.error(token.offset, "unclosed parenthesis")),
+                }
+            }
+            TokenKind::Filter(filter) => Ok(FilterExpr::Filter(filter.clone())),
+            TokenKind::Close | TokenKind::And | TokenKind::Or => {
+                Err(self.error(token.offset, "expected a filter"))
+            }
+        }
+    }
+}
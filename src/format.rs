@@ -0,0 +1,716 @@
+use crate::{
+    constants::LANGUAGES,
+    file::PackedFile,
+    toc::TocEntry,
+    tree::{self, TreeEntry},
+    unpack::find_boundary,
+};
+use serde::Serialize;
+use std::{
+    collections::{hash_map::RandomState, HashSet},
+    fmt,
+    hash::{BuildHasher, Hasher},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Marker shared by every format's preamble, used to recognise files written by codepack.
+pub(crate) const PROMPT_MARKER: &str = "representing an entire directory's contents.";
+
+/// Start and end of the note added before the files when the contents are numbered, around
+/// the separator after each number. Unpacking reads the separator back from it.
+pub(crate) const LINE_NUMBERS_NOTE: (&str, &str) = (
+    "Every line of a file's contents starts with its line number in the file and `",
+    "`, which are not part of the file.",
+);
+
+/// Whether `head`, the start of an existing file, is a pack codepack wrote, with or without
+/// its preamble. Markdown packs without a preamble, line numbers or parts can't be told apart
+/// from other Markdown files.
+pub(crate) fn is_pack(head: &str) -> bool {
+    let mut lines = head.lines();
+    let first = lines.next().unwrap_or("");
+    let note = first.strip_prefix("<!-- ").unwrap_or(first);
+    first.contains(PROMPT_MARKER)
+        || note.starts_with(LINE_NUMBERS_NOTE.0)
+        || (first.starts_with("This is part ") && first.ends_with(" It contains:"))
+        // XML
+        || first == "<files>"
+        || (first == "<!--" && lines.next().is_some_and(|line| line.starts_with("  This is part ")))
+        // JSON, and a JSON Lines record
+        || ["{\"files\":[", "{\"part\":{", "{\"tree\":"]
+            .iter()
+            .any(|start| first.starts_with(start))
+        || (first.starts_with("{\"path\":") && first.contains(",\"content\":"))
+        // Plain text, whose first delimiter follows the tree and table of contents if any
+        || find_boundary(head).is_some()
+}
+
+fn line_numbers_note(separator: &str) -> String {
+    format!(
+        "{}{}{}",
+        LINE_NUMBERS_NOTE.0, separator, LINE_NUMBERS_NOTE.1
+    )
+}
+
+/// Hooks used by `DirectoryProcessor` to lay out a pack.
+///
+/// A fresh formatter is created for every run, so implementations are free to keep state
+/// between calls (e.g. whether a separator is needed before the next file).
+pub trait OutputFormat {
+    /// Written once before any file.
+    fn write_header(&mut self, writer: &mut dyn Write, info: &PackInfo) -> io::Result<()>;
+
+    /// Written once for every file included in the pack.
+    fn write_file(&mut self, writer: &mut dyn Write, file: &PackedFile) -> io::Result<()>;
+
+    /// Written once after the last file, including the list of omitted files if any.
+    fn write_footer(&mut self, writer: &mut dyn Write, info: &PackInfo) -> io::Result<()>;
+
+    /// Lines `write_file` writes before the line holding the first line of a file's contents,
+    /// or `None` if contents are not written line for line, which leaves the table of
+    /// contents out.
+    fn content_offset(&self) -> Option<usize> {
+        None
+    }
+}
+
+/// What a formatter knows about the pack as a whole.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PackInfo {
+    /// False when the preamble is suppressed.
+    pub prompt: bool,
+    /// Token budget the pack was fitted into, if any.
+    pub max_tokens: Option<usize>,
+    /// Files left out of the pack to fit the token budget.
+    pub omitted: Vec<OmittedFile>,
+    /// Set when the pack is split into several parts.
+    pub part: Option<PartInfo>,
+    /// Files shown in the directory tree after the preamble, none if there is no tree.
+    pub tree: Vec<TreeEntry>,
+    /// Files listed in the table of contents after the tree, none if there is no table.
+    pub toc: Vec<TocEntry>,
+    /// Written after the line number starting every line of the contents, when they are
+    /// numbered.
+    pub line_numbers: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct PartInfo {
+    /// Starts at 1.
+    pub number: usize,
+    pub total: usize,
+    /// Labels of the files in this part, in order.
+    pub contents: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OmittedFile {
+    pub path: PathBuf,
+    /// Tokens the file would have cost in the pack.
+    pub tokens: usize,
+}
+
+impl PackInfo {
+    /// Writes which part of the pack this is and what it contains, if it is split.
+    fn write_part(&self, writer: &mut dyn Write, pack: &str, bullet: &str) -> io::Result<()> {
+        let Some(part) = &self.part else {
+            return Ok(());
+        };
+
+        writeln!(
+            writer,
+            "This is part {} of {} of {}. It contains:",
+            part.number, part.total, pack
+        )?;
+        for label in &part.contents {
+            writeln!(writer, "{}{}", bullet, label)?;
+        }
+        writeln!(writer)
+    }
+
+    /// Renders the directory tree, if there is one.
+    fn tree(&self) -> Option<String> {
+        (!self.tree.is_empty()).then(|| tree::render(&self.tree, &self.omitted))
+    }
+
+    /// Writes the table of contents as a plain list, prefixed by `bullet`, after `intro`.
+    fn write_toc(&self, writer: &mut dyn Write, intro: &str, bullet: &str) -> io::Result<()> {
+        if self.toc.is_empty() {
+            return Ok(());
+        }
+
+        writeln!(writer, "{}", intro)?;
+        for entry in self.toc_entries() {
+            writeln!(
+                writer,
+                "{}{}: line {}, {} lines, {} bytes",
+                bullet, entry.label, entry.line, entry.lines, entry.size
+            )?;
+        }
+        writeln!(writer)
+    }
+
+    /// The entries of the table of contents, without files omitted to fit the token budget.
+    fn toc_entries(&self) -> impl Iterator<Item = &TocEntry> {
+        let omitted: HashSet<&Path> = self
+            .omitted
+            .iter()
+            .map(|file| file.path.as_path())
+            .collect();
+        self.toc
+            .iter()
+            .filter(move |entry| !omitted.contains(entry.path.as_path()))
+    }
+
+    /// Writes the omitted files as a plain list, prefixed by `bullet`.
+    fn write_omitted(&self, writer: &mut dyn Write, bullet: &str) -> io::Result<()> {
+        if self.omitted.is_empty() {
+            return Ok(());
+        }
+
+        writeln!(
+            writer,
+            "\nThe following files were omitted to fit the token budget of {} tokens:",
+            self.max_tokens.unwrap_or_default()
+        )?;
+        for file in &self.omitted {
+            writeln!(
+                writer,
+                "{}{} ({} tokens)",
+                bullet,
+                file.path.display(),
+                file.tokens
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The built-in output formats.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Format {
+    /// Files delimited by a line holding a unique boundary and their path.
+    #[default]
+    Text,
+    /// Files under a heading, in fenced code blocks tagged with their language.
+    Markdown,
+    /// Files in `<file path="...">` elements, with contents in CDATA sections.
+    Xml,
+    /// A single JSON document with an object (metadata and content) per file.
+    Json,
+    /// One JSON object per line, per file.
+    JsonLines,
+}
+
+impl Format {
+    pub fn formatter(self) -> Box<dyn OutputFormat> {
+        match self {
+            Format::Text => Box::new(PlainText::new()),
+            _ => self.formatter_for(&[]),
+        }
+    }
+
+    /// Returns a formatter for a pack of `files`, which always lays them out byte for byte the
+    /// same way.
+    pub fn formatter_for(self, files: &[PackedFile]) -> Box<dyn OutputFormat> {
+        match self {
+            Format::Text => Box::new(PlainText::for_files(files)),
+            Format::Markdown => Box::new(Markdown),
+            Format::Xml => Box::new(Xml),
+            Format::Json => Box::new(Json::default()),
+            Format::JsonLines => Box::new(JsonLines),
+        }
+    }
+
+    /// File extension used for the default output file name.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Text => "txt",
+            Format::Markdown => "md",
+            Format::Xml => "xml",
+            Format::Json => "json",
+            Format::JsonLines => "jsonl",
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" | "txt" | "plain" => Ok(Format::Text),
+            "markdown" | "md" => Ok(Format::Markdown),
+            "xml" => Ok(Format::Xml),
+            "json" => Ok(Format::Json),
+            "jsonl" | "ndjson" | "json-lines" => Ok(Format::JsonLines),
+            _ => Err(format!(
+                "unknown format '{}' (expected one of: text, markdown, xml, json, jsonl)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Format::Text => "text",
+            Format::Markdown => "markdown",
+            Format::Xml => "xml",
+            Format::Json => "json",
+            Format::JsonLines => "jsonl",
+        })
+    }
+}
+
+/// Makes a fresh formatter for a pack of the given files.
+pub(crate) type NewFormatter = dyn Fn(&[PackedFile]) -> Box<dyn OutputFormat> + Send + Sync;
+
+/// Where the formatters of a processor come from: a built-in format, or a custom one.
+#[derive(Clone)]
+pub(crate) enum FormatterFactory {
+    Builtin(Format),
+    Custom(Arc<NewFormatter>),
+}
+
+impl FormatterFactory {
+    /// Returns a formatter for a pack of `files`.
+    pub fn formatter_for(&self, files: &[PackedFile]) -> Box<dyn OutputFormat> {
+        match self {
+            FormatterFactory::Builtin(format) => format.formatter_for(files),
+            FormatterFactory::Custom(new_formatter) => new_formatter(files),
+        }
+    }
+
+    /// Returns a formatter to measure packs with, before their files are known.
+    pub fn formatter(&self) -> Box<dyn OutputFormat> {
+        match self {
+            FormatterFactory::Builtin(format) => format.formatter(),
+            FormatterFactory::Custom(new_formatter) => new_formatter(&[]),
+        }
+    }
+}
+
+impl fmt::Debug for FormatterFactory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FormatterFactory::Builtin(format) => f.debug_tuple("Builtin").field(format).finish(),
+            FormatterFactory::Custom(_) => f.write_str("Custom"),
+        }
+    }
+}
+
+/// Returns the language tag for a file, inferred from its extension (or name, for
+/// extensionless files such as `Dockerfile`).
+pub fn language_for(path: &Path) -> Option<&'static str> {
+    let key = path
+        .extension()
+        .or_else(|| path.file_name())
+        .and_then(|s| s.to_str())?
+        .to_ascii_lowercase();
+
+    LANGUAGES
+        .iter()
+        .find(|(ext, _)| *ext == key)
+        .map(|(_, language)| *language)
+}
+
+/// Plain text, with files delimited MIME-style by a boundary that is unique to every pack.
+///
+/// Each file starts with a `--<boundary> <path>` line and its contents run up to the newline
+/// before the next boundary line, so contents are recovered byte for byte even when they
+/// contain something that looks like a delimiter. The pack ends with `--<boundary>--`.
+pub struct PlainText {
+    boundary: String,
+}
+
+impl PlainText {
+    pub fn new() -> Self {
+        Self::with_boundary(random_boundary())
+    }
+
+    /// Derives the boundary from the paths and contents of the files, so packing the same
+    /// files twice gives the same pack.
+    pub fn for_files(files: &[PackedFile]) -> Self {
+        for attempt in 0u64.. {
+            let (mut high, mut low) = (Fnv::new(attempt), Fnv::new(!attempt));
+            for file in files {
+                for hash in [&mut high, &mut low] {
+                    hash.write(file.label().as_bytes());
+                    hash.write(&[0]);
+                    hash.write(file.content.as_bytes());
+                    hash.write(&[0]);
+                }
+            }
+
+            let boundary = format!("{}{:016x}{:016x}", BOUNDARY_PREFIX, high.0, low.0);
+            let delimiter = format!("--{}", boundary);
+            if !files.iter().any(|file| file.content.contains(&delimiter)) {
+                return Self::with_boundary(boundary);
+            }
+        }
+        unreachable!("every attempt gives a different boundary")
+    }
+
+    pub fn with_boundary(boundary: String) -> Self {
+        Self { boundary }
+    }
+
+    pub fn boundary(&self) -> &str {
+        &self.boundary
+    }
+}
+
+impl Default for PlainText {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OutputFormat for PlainText {
+    fn write_header(&mut self, writer: &mut dyn Write, info: &PackInfo) -> io::Result<()> {
+        if info.prompt {
+            writeln!(writer, "This is a .txt file {}", PROMPT_MARKER)?;
+            writeln!(
+                writer,
+                "Each file starts with a line `--{0} <path>`, and the pack ends with `--{0}--`.",
+                self.boundary
+            )?;
+            writeln!(
+                writer,
+                "A file's contents are everything up to the newline before the next such line."
+            )?;
+            if let Some(separator) = &info.line_numbers {
+                writeln!(writer, "{}", line_numbers_note(separator))?;
+            }
+            if info.part.is_some() {
+                writeln!(
+                    writer,
+                    "A file too large for one part is split at line boundaries, with the lines each piece holds after its path."
+                )?;
+            }
+            writeln!(writer)?;
+        } else if let Some(separator) = &info.line_numbers {
+            // Kept without the preamble, as unpacking needs it to remove the numbers
+            writeln!(writer, "{}\n", line_numbers_note(separator))?;
+        }
+        info.write_part(writer, "the pack", "- ")?;
+        if let Some(tree) = info.tree() {
+            writeln!(writer, "Directory structure:\n{}", tree)?;
+        }
+        info.write_toc(
+            writer,
+            "Table of contents (the line of this pack each file's contents start on, its line count and size):",
+            "- ",
+        )
+    }
+
+    fn write_file(&mut self, writer: &mut dyn Write, file: &PackedFile) -> io::Result<()> {
+        writeln!(writer, "--{} {}", self.boundary, file.label())?;
+        writeln!(writer, "{}", file.content)
+    }
+
+    fn write_footer(&mut self, writer: &mut dyn Write, info: &PackInfo) -> io::Result<()> {
+        writeln!(writer, "--{}--", self.boundary)?;
+        info.write_omitted(writer, "- ")
+    }
+
+    fn content_offset(&self) -> Option<usize> {
+        Some(1)
+    }
+}
+
+/// Follows the code block of a Markdown file whose contents do not end with a newline, as
+/// the block needs one before its closing fence.
+pub(crate) const NO_NEWLINE_MARKER: &str = "\\ No newline at end of file";
+
+/// Prefix of every boundary, which lets a pack's boundary be found without its preamble.
+pub(crate) const BOUNDARY_PREFIX: &str = "codepack-";
+
+fn random_boundary() -> String {
+    // `RandomState` is seeded randomly by the standard library, so no extra dependency is needed.
+    let state = RandomState::new();
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos());
+    let (mut high, mut low) = (state.build_hasher(), state.build_hasher());
+    high.write_u128(nanos);
+    low.write_u64(high.finish());
+
+    format!(
+        "{}{:016x}{:016x}",
+        BOUNDARY_PREFIX,
+        high.finish(),
+        low.finish()
+    )
+}
+
+/// 64-bit FNV-1a, whose output is stable across platforms and Rust releases.
+struct Fnv(u64);
+
+impl Fnv {
+    fn new(seed: u64) -> Self {
+        let mut hash = Fnv(0xcbf2_9ce4_8422_2325);
+        hash.write(&seed.to_le_bytes());
+        hash
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+pub struct Markdown;
+
+impl OutputFormat for Markdown {
+    fn write_header(&mut self, writer: &mut dyn Write, info: &PackInfo) -> io::Result<()> {
+        if info.prompt {
+            writeln!(writer, "This is a Markdown file {}", PROMPT_MARKER)?;
+            writeln!(
+                writer,
+                "Each file is introduced by a heading with its path, followed by its contents in a fenced code block."
+            )?;
+            writeln!(
+                writer,
+                "A code block followed by `{}` has no newline before its closing fence in the file.",
+                NO_NEWLINE_MARKER
+            )?;
+            if let Some(separator) = &info.line_numbers {
+                writeln!(writer, "{}", line_numbers_note(separator))?;
+            }
+            writeln!(writer)?;
+        } else if let Some(separator) = &info.line_numbers {
+            writeln!(writer, "{}\n", line_numbers_note(separator))?;
+        }
+        info.write_part(writer, "a Markdown pack", "- ")?;
+        if let Some(tree) = info.tree() {
+            let fence = "`".repeat(longest_run(&tree, '`').max(2) + 1);
+            writeln!(
+                writer,
+                "# Directory structure\n\n{0}text\n{1}{0}\n",
+                fence, tree
+            )?;
+        }
+        if !info.toc.is_empty() {
+            writeln!(writer, "# Table of contents\n")?;
+            info.write_toc(
+                writer,
+                "Every file with the line of this pack its contents start on, its line count and size:",
+                "- ",
+            )?;
+        }
+        Ok(())
+    }
+
+    fn write_file(&mut self, writer: &mut dyn Write, file: &PackedFile) -> io::Result<()> {
+        // The fence must be longer than any run of backticks inside the file.
+        let fence = "`".repeat(longest_run(&file.content, '`').max(2) + 1);
+
+        writeln!(writer, "## {}\n", file.label())?;
+        writeln!(writer, "{}{}", fence, file.language.unwrap_or(""))?;
+        write!(writer, "{}", file.content)?;
+        let newline_added = !file.content.ends_with('\n');
+        if newline_added {
+            writeln!(writer)?;
+        }
+        writeln!(writer, "{}", fence)?;
+        if newline_added {
+            writeln!(writer, "{}", NO_NEWLINE_MARKER)?;
+        }
+        writeln!(writer)
+    }
+
+    fn write_footer(&mut self, writer: &mut dyn Write, info: &PackInfo) -> io::Result<()> {
+        info.write_omitted(writer, "- ")
+    }
+
+    /// The heading, the blank line after it and the opening fence.
+    fn content_offset(&self) -> Option<usize> {
+        Some(3)
+    }
+}
+
+pub struct Xml;
+
+impl OutputFormat for Xml {
+    fn write_header(&mut self, writer: &mut dyn Write, info: &PackInfo) -> io::Result<()> {
+        if info.prompt {
+            writeln!(
+                writer,
+                "<!-- This is an XML file {} Each file is a <file> element with its path, and its contents in a CDATA section. -->",
+                PROMPT_MARKER
+            )?;
+        }
+        if let Some(separator) = &info.line_numbers {
+            writeln!(writer, "<!-- {} -->", line_numbers_note(separator))?;
+        }
+        if info.part.is_some() {
+            writeln!(writer, "<!--")?;
+            info.write_part(writer, "the pack", "  ")?;
+            writeln!(writer, "-->")?;
+        }
+        writeln!(writer, "<files>")?;
+        if let Some(tree) = info.tree() {
+            writeln!(
+                writer,
+                "<tree><![CDATA[{}]]></tree>",
+                tree.replace("]]>", "]]]]><![CDATA[>")
+            )?;
+        }
+        for entry in info.toc_entries() {
+            writeln!(
+                writer,
+                "<toc path=\"{}\" line=\"{}\" lines=\"{}\" size=\"{}\"/>",
+                escape_xml_attribute(&entry.label),
+                entry.line,
+                entry.lines,
+                entry.size
+            )?;
+        }
+        Ok(())
+    }
+
+    fn write_file(&mut self, writer: &mut dyn Write, file: &PackedFile) -> io::Result<()> {
+        write!(
+            writer,
+            "<file path=\"{}\"",
+            escape_xml_attribute(&file.path.display().to_string())
+        )?;
+        if file.truncated {
+            write!(writer, " truncated=\"true\"")?;
+        }
+        if let Some(chunk) = &file.chunk {
+            write!(writer, " lines=\"{}\"", chunk)?;
+        }
+        // `]]>` cannot appear inside CDATA, so split it across two sections.
+        writeln!(
+            writer,
+            "><![CDATA[{}]]></file>",
+            file.content.replace("]]>", "]]]]><![CDATA[>")
+        )
+    }
+
+    fn write_footer(&mut self, writer: &mut dyn Write, info: &PackInfo) -> io::Result<()> {
+        for file in &info.omitted {
+            writeln!(
+                writer,
+                "<omitted path=\"{}\" tokens=\"{}\"/>",
+                escape_xml_attribute(&file.path.display().to_string()),
+                file.tokens
+            )?;
+        }
+        writeln!(writer, "</files>")
+    }
+
+    /// Contents start on the line of their `<file>` element.
+    fn content_offset(&self) -> Option<usize> {
+        Some(0)
+    }
+}
+
+#[derive(Default)]
+pub struct Json {
+    files_written: usize,
+}
+
+impl OutputFormat for Json {
+    fn write_header(&mut self, writer: &mut dyn Write, info: &PackInfo) -> io::Result<()> {
+        write!(writer, "{{")?;
+        if info.prompt {
+            write!(
+                writer,
+                "\"description\":{},",
+                serde_json::to_string(&format!(
+                    "This is a JSON file {} Each entry in `files` holds a file's path, metadata and contents.{}",
+                    PROMPT_MARKER,
+                    match &info.line_numbers {
+                        Some(separator) => format!(" {}", line_numbers_note(separator)),
+                        None => String::new(),
+                    }
+                ))?
+            )?;
+        }
+        if let Some(part) = &info.part {
+            write!(writer, "\"part\":")?;
+            serde_json::to_writer(&mut *writer, part)?;
+            write!(writer, ",")?;
+        }
+        if let Some(tree) = info.tree() {
+            write!(writer, "\"tree\":{},", serde_json::to_string(&tree)?)?;
+        }
+        write!(writer, "\"files\":[")
+    }
+
+    fn write_file(&mut self, writer: &mut dyn Write, file: &PackedFile) -> io::Result<()> {
+        if self.files_written > 0 {
+            write!(writer, ",")?;
+        }
+        writeln!(writer)?;
+        serde_json::to_writer(&mut *writer, file)?;
+        self.files_written += 1;
+        Ok(())
+    }
+
+    fn write_footer(&mut self, writer: &mut dyn Write, info: &PackInfo) -> io::Result<()> {
+        write!(writer, "\n]")?;
+        if !info.omitted.is_empty() {
+            write!(writer, ",\"omitted\":")?;
+            serde_json::to_writer(&mut *writer, &info.omitted)?;
+        }
+        writeln!(writer, "}}")
+    }
+}
+
+/// JSON Lines carries no preamble, so every line is a file record.
+pub struct JsonLines;
+
+impl OutputFormat for JsonLines {
+    fn write_header(&mut self, _writer: &mut dyn Write, _info: &PackInfo) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn write_file(&mut self, writer: &mut dyn Write, file: &PackedFile) -> io::Result<()> {
+        serde_json::to_writer(&mut *writer, file)?;
+        writeln!(writer)
+    }
+
+    /// Omitted files are only reported in the summary, as every line must be a file record.
+    fn write_footer(&mut self, _writer: &mut dyn Write, _info: &PackInfo) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn longest_run(haystack: &str, needle: char) -> usize {
+    let (mut longest, mut current) = (0, 0);
+    for c in haystack.chars() {
+        if c == needle {
+            current += 1;
+            longest = longest.max(current);
+        } else {
+            current = 0;
+        }
+    }
+    longest
+}
+
+fn escape_xml_attribute(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,261 @@
+use crate::error::{Error, Result};
+use gix::{
+    bstr::BStr,
+    revision::plumbing::Spec,
+    revision::walk::Sorting,
+    status::{tree_index::TrackRenames, UntrackedFiles},
+    traverse::commit::simple::CommitTimeOrder,
+    ObjectId,
+};
+use std::{
+    collections::HashSet,
+    convert::Infallible,
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+/// Files of the git repository holding the packed directory that a pack is restricted to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GitSelection {
+    /// Files changed in the working tree since a commit, such as `HEAD` or `main`, with
+    /// files not tracked yet.
+    Changed(String),
+    /// Files with changes staged in the index.
+    Staged,
+    /// Files changed between two commits, as `A..B`, or `A...B` for the changes on `B`
+    /// since it forked from `A`.
+    Range(String),
+}
+
+impl GitSelection {
+    /// Checks the revisions before they are looked up, so none is taken for an option.
+    pub(crate) fn validate(&self) -> Result<()> {
+        let (revision, expected) = match self {
+            GitSelection::Changed(reference) => (reference, "a commit such as HEAD"),
+            GitSelection::Staged => return Ok(()),
+            GitSelection::Range(range) => (range, "a range such as main..HEAD"),
+        };
+        let valid = !revision.is_empty()
+            && !revision.starts_with('-')
+            && (!matches!(self, GitSelection::Range(_)) || revision.contains(".."));
+        if !valid {
+            return Err(Error::InvalidOptions(format!(
+                "invalid git revision '{}' (expected {})",
+                revision, expected
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for GitSelection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GitSelection::Changed(reference) => write!(f, "files changed since {}", reference),
+            GitSelection::Staged => f.write_str("staged files"),
+            GitSelection::Range(range) => write!(f, "files changed in {}", range),
+        }
+    }
+}
+
+/// The files picked by git below the packed directory, relative to it.
+///
+/// Files deleted by the changes are listed too, though the walk does not find them.
+#[derive(Debug, Default)]
+pub(crate) struct GitFiles {
+    files: HashSet<PathBuf>,
+    /// Every directory holding one of the files.
+    dirs: HashSet<PathBuf>,
+}
+
+impl GitFiles {
+    pub fn contains(&self, relative_path: &Path) -> bool {
+        self.files.contains(relative_path)
+    }
+
+    /// Whether the directory at `relative_path` holds any of the files.
+    pub fn holds(&self, relative_path: &Path) -> bool {
+        relative_path.as_os_str().is_empty() || self.dirs.contains(relative_path)
+    }
+}
+
+/// Lists the files any of `selections` picks below `directory_path`.
+pub(crate) fn select(directory_path: &Path, selections: &[GitSelection]) -> Result<GitFiles> {
+    let repo = Repository::discover(directory_path)?;
+    let mut files = HashSet::new();
+    for selection in selections {
+        match selection {
+            GitSelection::Changed(reference) => {
+                let tree = repo.tree(reference)?;
+                files.extend(repo.status(tree)?);
+            }
+            GitSelection::Staged => files.extend(repo.staged()?),
+            GitSelection::Range(range) => {
+                let spec = repo.inner.rev_parse(range.as_str()).map_err(git_error)?;
+                let (from, to) = match spec.detach() {
+                    Spec::Range { from, to } => (from, to),
+                    Spec::Merge { theirs, ours } => {
+                        let base = repo.inner.merge_base(theirs, ours).map_err(git_error)?;
+                        (base.detach(), ours)
+                    }
+                    _ => return Err(Error::Git(format!("'{}' is not a range", range))),
+                };
+                files.extend(repo.changes(Some(from), to)?);
+            }
+        }
+    }
+
+    let dirs = files
+        .iter()
+        .flat_map(|file: &PathBuf| file.ancestors().skip(1))
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .map(Path::to_path_buf)
+        .collect();
+    Ok(GitFiles { files, dirs })
+}
+
+/// Lists the files below `directory_path` changed by each commit of the history of `HEAD`,
+/// newest first, so a file shows up once for every commit that changed it. Merges are
+/// skipped, as `git log` does.
+pub(crate) fn history(directory_path: &Path) -> Result<Vec<PathBuf>> {
+    let mut repo = Repository::discover(directory_path)?;
+    // Every commit is looked up again when its parent is diffed.
+    repo.inner.object_cache_size_if_unset(4 << 20);
+    let head = repo.inner.head_id().map_err(git_error)?;
+
+    let mut files = Vec::new();
+    let commits = repo
+        .inner
+        .rev_walk([head])
+        .sorting(Sorting::ByCommitTime(CommitTimeOrder::NewestFirst))
+        .all()
+        .map_err(git_error)?;
+    for commit in commits {
+        let commit = commit.map_err(git_error)?;
+        let mut parents = commit.parent_ids();
+        let parent = parents.next().map(|id| id.detach());
+        if parents.next().is_some() {
+            continue;
+        }
+        files.extend(repo.changes(parent, commit.id)?);
+    }
+    Ok(files)
+}
+
+/// The repository holding a packed directory, which paths are made relative to.
+struct Repository {
+    inner: gix::Repository,
+    /// Path of the directory in the working tree.
+    prefix: PathBuf,
+}
+
+impl Repository {
+    fn discover(directory_path: &Path) -> Result<Self> {
+        let inner = gix::discover(directory_path).map_err(git_error)?;
+        let workdir = inner
+            .workdir()
+            .ok_or_else(|| Error::Git("the repository has no working tree".to_string()))?;
+        let workdir = fs::canonicalize(workdir).map_err(git_error)?;
+        let directory_path = fs::canonicalize(directory_path).map_err(git_error)?;
+        let prefix = directory_path
+            .strip_prefix(workdir)
+            .map_err(git_error)?
+            .to_path_buf();
+        Ok(Self { inner, prefix })
+    }
+
+    /// Makes a path of the repository relative to the packed directory, or `None` if it is
+    /// not below it. Paths are taken as the bytes stored in the repository.
+    fn relative(&self, path: &BStr) -> Option<PathBuf> {
+        gix::path::from_bstr(path)
+            .strip_prefix(&self.prefix)
+            .ok()
+            .map(Path::to_path_buf)
+    }
+
+    /// The tree of the commit `revision` names.
+    fn tree(&self, revision: &str) -> Result<ObjectId> {
+        let tree = self
+            .inner
+            .rev_parse_single(revision)
+            .map_err(git_error)?
+            .object()
+            .map_err(git_error)?
+            .peel_to_tree()
+            .map_err(git_error)?;
+        Ok(tree.id)
+    }
+
+    /// Files changed between two commits, the first of which is empty if there is none.
+    fn changes(&self, from: Option<ObjectId>, to: impl Into<ObjectId>) -> Result<Vec<PathBuf>> {
+        let tree = |commit: ObjectId| -> Result<gix::Tree<'_>> {
+            self.inner
+                .find_object(commit)
+                .map_err(git_error)?
+                .peel_to_tree()
+                .map_err(git_error)
+        };
+        let from = from.map(tree).transpose()?;
+        let to = tree(to.into())?;
+        let changes = self
+            .inner
+            .diff_tree_to_tree(from.as_ref(), &to, gix::diff::Options::default())
+            .map_err(git_error)?;
+        Ok(changes
+            .iter()
+            .filter_map(|change| self.relative(change.location()))
+            .collect())
+    }
+
+    /// Files of the working tree that differ from `tree`, in the index or on disk, with files
+    /// neither tracked nor ignored.
+    fn status(&self, tree: ObjectId) -> Result<Vec<PathBuf>> {
+        let items = self
+            .inner
+            .status(gix::progress::Discard)
+            .map_err(git_error)?
+            .head_tree(tree)
+            .untracked_files(UntrackedFiles::Files)
+            .index_worktree_submodules(None)
+            .tree_index_track_renames(TrackRenames::Disabled)
+            .into_iter(None)
+            .map_err(git_error)?;
+
+        let mut files = Vec::new();
+        for item in items {
+            let item = item.map_err(git_error)?;
+            // Files only touched on disk, with the same contents, are not changed.
+            if let gix::status::Item::IndexWorktree(change) = &item {
+                if change.summary().is_none() {
+                    continue;
+                }
+            }
+            files.extend(self.relative(item.location()));
+        }
+        Ok(files)
+    }
+
+    /// Files whose changes are staged in the index.
+    fn staged(&self) -> Result<Vec<PathBuf>> {
+        let head = self.inner.head_tree_id_or_empty().map_err(git_error)?;
+        let index = self.inner.index_or_empty().map_err(git_error)?;
+        let mut files = Vec::new();
+        self.inner
+            .tree_index_status(
+                &head,
+                &index,
+                None,
+                TrackRenames::Disabled,
+                |change, _, _| {
+                    files.extend(self.relative(change.location()));
+                    Ok::<_, Infallible>(gix::diff::index::Action::Continue)
+                },
+            )
+            .map_err(git_error)?;
+        Ok(files)
+    }
+}
+
+fn git_error(err: impl fmt::Display) -> Error {
+    Error::Git(err.to_string())
+}
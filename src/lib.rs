@@ -1,153 +1,502 @@
+mod binary;
+mod budget;
+mod builder;
 mod constants;
-
-use crate::constants::{DEFAULT_EXCLUSIONS, UNSUPPORTED_EXTENSIONS};
-use anyhow::Result;
-use globset::{GlobBuilder, GlobMatcher};
-use ignore::WalkBuilder;
+mod encoding;
+mod error;
+mod file;
+mod filter;
+mod format;
+mod git;
+mod metadata;
+mod pack;
+mod sort;
+mod split;
+mod toc;
+mod tokens;
+mod tree;
+mod truncate;
+mod unpack;
+
+pub use crate::budget::{BudgetStrategy, TokenBudget};
+pub use crate::builder::DirectoryProcessorBuilder;
+use crate::constants::UNSUPPORTED_EXTENSIONS;
+pub use crate::error::{Error, Result};
+pub use crate::file::{Chunk, LineNumbers, PackedFile};
+use crate::filter::{Candidate, Content, ContentScan};
+pub use crate::filter::{Field, Filter, FilterExpr, Pattern, PatternKind};
+use crate::format::{is_pack, FormatterFactory};
+pub use crate::format::{
+    language_for, Format, Json, JsonLines, Markdown, OmittedFile, OutputFormat, PackInfo, PartInfo,
+    PlainText, Xml,
+};
+use crate::git::GitFiles;
+pub use crate::git::GitSelection;
+pub use crate::metadata::{Comparison, Timestamp};
+use crate::pack::PackWriter;
+pub use crate::pack::{FileSummary, PackSummary, SkippedFile};
+pub use crate::sort::SortOrder;
+use crate::split::split;
+pub use crate::split::{part_path, SplitLimit};
+pub use crate::toc::TocEntry;
+pub use crate::tokens::{Bpe, Estimate, Tokenizer, TokenizerKind};
+pub use crate::tree::{TreeEntry, TreeMode};
+pub use crate::truncate::{FileSizeLimit, TruncateStrategy};
+pub use crate::unpack::Unpacker;
+use encoding_rs::{Encoding, UTF_8};
+use globset::{GlobMatcher, GlobSet};
+use ignore::{WalkBuilder, WalkState};
 use indicatif::{ProgressBar, ProgressStyle};
 use log::debug;
 use std::{
-    fs::File,
-    io::{BufRead, BufWriter, Write},
-    path::Path,
-    sync::Arc,
+    collections::HashSet,
+    fs::{self, File},
+    io::{self, BufRead, BufWriter, Cursor, Read, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex, PoisonError,
+    },
+    thread,
 };
 
-#[derive(Debug)]
-pub enum Filter {
-    FileName(String),        // Matches file name
-    PathContains(String),    // Matches a substring in the path
-    ContentContains(String), // Matches a substring in the file content
-}
+/// Output path that writes the pack to stdout instead of a file.
+pub const STDOUT: &str = "-";
+
+/// Files larger than this are matched against content filters a few lines at a time,
+/// rather than read whole.
+const STREAM_THRESHOLD: u64 = 1 << 20;
+
+/// Bytes read from an existing output file to tell whether it is a pack.
+const PACK_HEAD_LEN: u64 = 1 << 20;
 
+#[derive(Debug)]
 pub struct DirectoryProcessor {
     extensions: Arc<Vec<String>>,
     excluded_matchers: Arc<Vec<GlobMatcher>>,
     suppress_prompt: bool,
     output: String,
     force: bool,
-    filters: Vec<Filter>,
+    /// Set once replacing the output file was confirmed, which does not extend to parts.
+    confirmed: bool,
+    filters: Vec<FilterExpr>,
+    formatters: FormatterFactory,
+    tokenizer: TokenizerKind,
+    budget: Option<TokenBudget>,
+    split: Option<SplitLimit>,
+    sort: SortOrder,
+    sort_first: GlobSet,
+    tree: TreeMode,
+    toc: bool,
+    line_numbers: Option<LineNumbers>,
+    force_text: GlobSet,
+    strict: bool,
+    encodings: Vec<(GlobMatcher, &'static Encoding)>,
+    max_file_size: Option<(FileSizeLimit, TruncateStrategy)>,
+    git: Vec<GitSelection>,
 }
 
-fn get_default_exclusions() -> Vec<String> {
-    DEFAULT_EXCLUSIONS.iter().map(|s| s.to_string()).collect()
-}
+impl DirectoryProcessor {
+    /// Starts configuring a processor, with every option at its default.
+    pub fn builder() -> DirectoryProcessorBuilder {
+        DirectoryProcessorBuilder::default()
+    }
 
-fn create_exclusions(excluded_files: Vec<String>) -> Vec<String> {
-    let mut exclusions = get_default_exclusions();
-    exclusions.extend(excluded_files);
-    exclusions.extend(UNSUPPORTED_EXTENSIONS.iter().map(|s| format!("*.{}", s)));
+    pub fn run(&self, directory_path: &Path) -> Result<PackSummary> {
+        let output = Path::new(&self.output);
+        if self.writes_to_stdout() {
+            let mut summary = self.write_to(directory_path, io::stdout().lock())?;
+            summary.outputs.push(output.to_owned());
+            return Ok(summary);
+        }
 
-    exclusions
-}
+        // Never ask from the library: unless confirmed or forced, only empty files and
+        // previous packs are replaced
+        if !self.validate_output_file(
+            self.output.clone(),
+            self.force || self.confirmed,
+            &mut io::empty(),
+            &mut io::sink(),
+        )? {
+            return Err(Error::OutputExists(output.to_owned()));
+        }
 
-impl DirectoryProcessor {
-    pub fn new(
-        extensions: Vec<String>,
-        excluded_files: Vec<String>,
-        suppress_prompt: bool,
-        output: String,
-        force: bool,
-        filters: Vec<Filter>,
-    ) -> Self {
-        let excluded_files = create_exclusions(excluded_files);
-        let excluded_matchers: Vec<GlobMatcher> = excluded_files
-            .into_iter()
-            .map(|pattern| {
-                GlobBuilder::new(&pattern)
-                    .build()
-                    .unwrap()
-                    .compile_matcher()
-            })
-            .collect();
+        let (files, info, skipped) = self.select(directory_path)?;
+        let parts = match self.split {
+            Some(limit) => split(
+                files,
+                limit,
+                &self.formatters,
+                self.tokenizer.tokenizer().as_ref(),
+                &info,
+                output,
+            )?,
+            None => vec![files],
+        };
+        if self.split.is_some() {
+            for stale in self.check_parts(output, parts.len())? {
+                debug!("Removing {} left from an earlier run", stale.display());
+                fs::remove_file(&stale).map_err(Error::io(&stale))?;
+            }
+        }
 
-        Self {
-            extensions: Arc::new(extensions),
-            excluded_matchers: Arc::new(excluded_matchers),
-            suppress_prompt,
-            output,
-            force,
-            filters,
+        if parts.len() == 1 {
+            let mut summary = self.write_pack(output, &parts[0], &info)?;
+            summary.skipped = skipped;
+            return Ok(summary);
         }
+
+        let mut summary = PackSummary::default();
+        for (i, files) in parts.iter().enumerate() {
+            let mut part_info = info.clone();
+            part_info.part = Some(PartInfo {
+                number: i + 1,
+                total: parts.len(),
+                contents: files.iter().map(PackedFile::label).collect(),
+            });
+            // Only the last part lists the files omitted from the whole pack
+            if i + 1 < parts.len() {
+                part_info.omitted.clear();
+            }
+            // Only the first part shows the directory tree
+            if i > 0 {
+                part_info.tree.clear();
+            }
+            summary.merge(self.write_pack(&part_path(output, i + 1), files, &part_info)?);
+        }
+        summary.omitted = info.omitted;
+        summary.skipped = skipped;
+        Ok(summary)
     }
 
-    pub fn run(&self, directory_path: &Path) -> Result<usize> {
-        // Validate the output file if provided
-        match self.validate_output_file(
-            self.output.clone(),
-            self.force,
-            &mut std::io::BufReader::new(std::io::stdin().lock()),
-            &mut std::io::stdout(),
-        ) {
-            Ok(false) => std::process::exit(0), // exit if 'n'
-            Err(err) => eprintln!("Error during output file validation: {}", err),
-            _ => {}
+    /// Writes the pack of a directory to `writer` instead of the output file.
+    ///
+    /// The output path and overwrite settings are ignored, and the pack cannot be split.
+    pub fn write_to<W: Write>(&self, directory_path: &Path, writer: W) -> Result<PackSummary> {
+        if self.split.is_some() {
+            return Err(Error::InvalidOptions(
+                "a pack written to a single writer cannot be split into parts".to_string(),
+            ));
         }
 
+        let (files, info, skipped) = self.select(directory_path)?;
+        let mut summary = self
+            .write_files(BufWriter::new(writer), &files, &info)
+            .map_err(Error::Write)?;
+        summary.skipped = skipped;
+        Ok(summary)
+    }
+
+    /// Returns the files that would be packed from a directory, in pack order, without
+    /// writing anything. Files that cannot be read are left out unless the processor is
+    /// strict.
+    pub fn collect(&self, directory_path: &Path) -> Result<Vec<PackedFile>> {
+        Ok(self.select(directory_path)?.0)
+    }
+
+    /// Walks the directory and reads the files to pack, leaving out those over the budget.
+    fn select(
+        &self,
+        directory_path: &Path,
+    ) -> Result<(Vec<PackedFile>, PackInfo, Vec<SkippedFile>)> {
         let pb = ProgressBar::new(0);
         pb.set_style(
             ProgressStyle::default_bar()
-                .template("{msg} [{elapsed_precise}] {bar:40.cyan} {percent}%")?
+                .template("{msg} [{elapsed_precise}] {bar:40.cyan} {percent}%")
+                .expect("progress bar template is valid")
                 .progress_chars("=>-"),
         );
 
+        let git = if self.git.is_empty() {
+            None
+        } else {
+            Some(git::select(directory_path, &self.git)?)
+        };
+        let (mut file_paths, excluded) = self.walk(directory_path, git.as_ref());
+        sort::sort(&mut file_paths, self.sort, &self.sort_first, directory_path);
+        let (mut files, skipped) = self.read_files(&file_paths, directory_path, &pb)?;
+
+        pb.finish_with_message("Directory processing complete");
+
+        let mut info = PackInfo {
+            prompt: !self.suppress_prompt,
+            line_numbers: self
+                .line_numbers
+                .as_ref()
+                .map(|line_numbers| line_numbers.separator.clone()),
+            ..Default::default()
+        };
+        if self.tree != TreeMode::Off {
+            info.tree = self.tree_entries(&files, &file_paths, &excluded, directory_path);
+        }
+        if self.toc {
+            // Reserve room for the table when fitting the budget; it is filled in when the
+            // files are laid out
+            info.toc = toc::estimate(&files);
+        }
+        if let Some(budget) = &self.budget {
+            let tokenizer = self.tokenizer.tokenizer();
+            files = budget
+                .fit(files, &self.formatters, tokenizer.as_ref(), &mut info)
+                .map_err(Error::Write)?;
+        }
+
+        Ok((files, info, skipped))
+    }
+
+    /// Walks the directory on every core, returning the files to read and the other files
+    /// found, both sorted by path. With `git`, only the files it picks are read.
+    fn walk(&self, directory_path: &Path, git: Option<&GitFiles>) -> (Vec<PathBuf>, Vec<PathBuf>) {
+        let file_paths = Mutex::new(Vec::new());
+        let excluded = Mutex::new(Vec::new());
+
         // Walk the directory, filtering files and directories
-        let walker = WalkBuilder::new(directory_path)
+        WalkBuilder::new(directory_path)
             .standard_filters(true)
-            .build();
+            .build_parallel()
+            .run(|| {
+                Box::new(|entry| {
+                    let entry = match entry {
+                        Ok(e) => e,
+                        Err(err) => {
+                            eprintln!("Error reading entry: {}", err);
+                            return WalkState::Continue;
+                        }
+                    };
 
-        let mut file_paths = Vec::new();
+                    let path = entry.path();
 
-        for entry in walker {
-            let entry = match entry {
-                Ok(e) => e,
-                Err(err) => {
-                    eprintln!("Error reading entry: {}", err);
-                    continue;
-                }
-            };
+                    // Skip directories matching exclusion patterns
+                    if path.is_dir()
+                        && self
+                            .excluded_matchers
+                            .iter()
+                            .any(|matcher| matcher.is_match(path))
+                    {
+                        return WalkState::Continue; // Do not process this directory
+                    }
+
+                    let relative_path = path.strip_prefix(directory_path).unwrap_or(path);
+                    // Directories without changes are only walked to show them in the tree
+                    if let Some(git) = git {
+                        if path.is_dir() && self.tree != TreeMode::All && !git.holds(relative_path)
+                        {
+                            return WalkState::Skip;
+                        }
+                    }
+
+                    // Process files matching criteria
+                    if path.is_file() {
+                        let paths = if self.selects(path, relative_path)
+                            && git.is_none_or(|git| git.contains(relative_path))
+                        {
+                            &file_paths
+                        } else {
+                            &excluded
+                        };
+                        paths
+                            .lock()
+                            .unwrap_or_else(PoisonError::into_inner)
+                            .push(path.to_owned());
+                    }
+                    WalkState::Continue
+                })
+            });
+
+        // Threads find files in any order, so sort them for a reproducible pack
+        let [file_paths, excluded] = [file_paths, excluded].map(|paths| {
+            let mut paths = paths.into_inner().unwrap_or_else(PoisonError::into_inner);
+            paths.sort();
+            paths
+        });
+        (file_paths, excluded)
+    }
 
-            let path = entry.path();
+    /// Lists the files shown in the directory tree, relative to the packed directory.
+    fn tree_entries(
+        &self,
+        files: &[PackedFile],
+        file_paths: &[PathBuf],
+        excluded: &[PathBuf],
+        directory_path: &Path,
+    ) -> Vec<TreeEntry> {
+        let mut entries: Vec<TreeEntry> = files
+            .iter()
+            .map(|file| TreeEntry {
+                path: file.path.clone(),
+                excluded: false,
+            })
+            .collect();
 
-            // Skip directories matching exclusion patterns
-            if path.is_dir()
-                && self
-                    .excluded_matchers
+        if self.tree == TreeMode::All {
+            // Files left out by their content were walked and read, but are not in `files`
+            let packed: HashSet<&Path> = files.iter().map(|file| file.path.as_path()).collect();
+            entries.extend(
+                file_paths
                     .iter()
-                    .any(|matcher| matcher.is_match(path))
-            {
-                continue; // Do not process this directory
+                    .chain(excluded)
+                    .map(|path| path.strip_prefix(directory_path).unwrap_or(path))
+                    .filter(|path| !packed.contains(path))
+                    .map(|path| TreeEntry {
+                        path: path.to_owned(),
+                        excluded: true,
+                    }),
+            );
+        }
+        entries
+    }
+
+    /// Reads the files to pack on a pool of threads, keeping the order of `file_paths`.
+    ///
+    /// Files that content filters may leave out are matched first, so the progress bar only
+    /// counts the files selected. If several files fail, the error of the first one in that
+    /// order is returned.
+    fn read_files(
+        &self,
+        file_paths: &[PathBuf],
+        directory_path: &Path,
+        pb: &ProgressBar,
+    ) -> Result<(Vec<PackedFile>, Vec<SkippedFile>)> {
+        // Tokens are counted here, on every core, rather than as the pack is written
+        let tokenizer = self.tokenizer.tokenizer();
+        let count_tokens = |mut outcome: Result<Option<ReadOutcome>>| {
+            if let Ok(Some(ReadOutcome::Packed(file))) = &mut outcome {
+                file.tokens = Some(tokenizer.count(&file.content));
             }
+            outcome
+        };
 
-            // Process files matching criteria
-            if path.is_file() && self.should_process_file(path) {
-                file_paths.push(path.to_owned());
+        // What became of every file, `None` while it is still to be read
+        let mut read: Vec<Result<Option<ReadOutcome>>> =
+            file_paths.iter().map(|_| Ok(None)).collect();
+
+        let unmatched: Vec<usize> = (0..file_paths.len())
+            .filter(|&i| {
+                let path = &file_paths[i];
+                self.filter_matches(&Candidate::new(
+                    path,
+                    path.strip_prefix(directory_path).unwrap_or(path),
+                    Content::Unread,
+                ))
+                .is_none()
+            })
+            .collect();
+        if !unmatched.is_empty() {
+            pb.set_message("Matching contents");
+            pb.set_length(unmatched.len() as u64);
+            let matched = par_map(&unmatched, pb, |&i| {
+                count_tokens(self.match_contents(&file_paths[i], directory_path))
+            });
+            for (i, outcome) in unmatched.into_iter().zip(matched) {
+                read[i] = outcome;
             }
+            pb.reset();
         }
 
-        pb.set_length(file_paths.len() as u64);
-
-        let output_file = File::create(self.output.clone())?;
-        let mut writer = BufWriter::new(output_file);
+        let to_read: Vec<usize> = (0..file_paths.len())
+            .filter(|&i| matches!(read[i], Ok(None)))
+            .collect();
+        let selected = read
+            .iter()
+            .filter(|outcome| !matches!(outcome, Ok(Some(ReadOutcome::Filtered))))
+            .count();
+        debug!(
+            "{} of {} files selected by the filters",
+            selected,
+            file_paths.len()
+        );
+        pb.set_message("Reading files");
+        pb.set_length(selected as u64);
+        pb.set_position((selected - to_read.len()) as u64);
+        let outcomes = par_map(&to_read, pb, |&i| {
+            count_tokens(
+                self.process_file(&file_paths[i], directory_path, true)
+                    .map(Some),
+            )
+        });
+        for (i, outcome) in to_read.into_iter().zip(outcomes) {
+            read[i] = outcome;
+        }
 
-        if !self.suppress_prompt {
-            writeln!(
-                writer,
-                "This is a .txt file representing an entire directory's contents."
-            )?;
-            writeln!(writer, "Each file is separated by a line with its path.\n")?;
+        let mut files = Vec::new();
+        let mut skipped = Vec::new();
+        for (i, outcome) in read.into_iter().enumerate() {
+            let reason = match outcome {
+                Ok(Some(ReadOutcome::Packed(file))) => {
+                    files.push(file);
+                    continue;
+                }
+                Ok(Some(ReadOutcome::Filtered)) => continue,
+                Ok(Some(ReadOutcome::Skipped(reason))) => reason,
+                Ok(None) => unreachable!("every file selected is read"),
+                // Fail before anything is written
+                Err(err) if self.strict => return Err(err),
+                Err(err) => skip_reason(&err),
+            };
+            let path = &file_paths[i];
+            debug!("Skipping {}: {}", path.display(), reason);
+            skipped.push(SkippedFile {
+                path: path.strip_prefix(directory_path).unwrap_or(path).to_owned(),
+                reason,
+            });
         }
+        Ok((files, skipped))
+    }
+
+    fn write_pack(
+        &self,
+        path: &Path,
+        files: &[PackedFile],
+        info: &PackInfo,
+    ) -> Result<PackSummary> {
+        let writer = BufWriter::new(File::create(path).map_err(Error::io(path))?);
+        let mut summary = self
+            .write_files(writer, files, info)
+            .map_err(Error::io(path))?;
+        summary.outputs.push(path.to_owned());
+
+        Ok(summary)
+    }
 
-        for path in &file_paths {
-            self.process_and_write_file(path, &mut writer, &pb)?;
+    fn write_files<W: Write>(
+        &self,
+        writer: W,
+        files: &[PackedFile],
+        info: &PackInfo,
+    ) -> io::Result<PackSummary> {
+        let mut info = info.clone();
+        if self.toc {
+            info.toc = toc::build(&self.formatters, files, &info)?;
         }
+        let info = &info;
 
-        pb.finish_with_message("Directory processing complete");
+        let mut pack = PackWriter::new(
+            writer,
+            self.formatters.formatter_for(files),
+            self.tokenizer.tokenizer(),
+        );
 
-        Ok(file_paths.len())
+        pack.header(info)?;
+        for file in files {
+            pack.file(file)?;
+        }
+        pack.finish(info)
     }
 
+    /// Whether the pack goes to stdout, which is requested with an output of `-`.
+    pub fn writes_to_stdout(&self) -> bool {
+        self.output == STDOUT
+    }
+
+    /// Whether a file would be read for the pack, judging by its path alone. Path regexes
+    /// and globs match `path` as if it were relative to the packed directory.
     pub fn should_process_file(&self, path: &Path) -> bool {
+        self.selects(path, path)
+    }
+
+    /// Whether the file at `path`, found at `relative_path` below the packed directory,
+    /// should be read.
+    fn selects(&self, path: &Path, relative_path: &Path) -> bool {
         // Check if the path is empty
         if path.to_str().unwrap_or("").is_empty() {
             return false;
@@ -166,60 +515,88 @@ impl DirectoryProcessor {
             }
         }
 
-        let path_str = path.to_string_lossy();
-
-        // If there are no filters, and not excluded, always include
-        if self.filters.is_empty() {
-            if self.extensions.is_empty() {
-                debug!("Processing file without filters: {}", path_str);
-                return true;
-            }
-            debug!("Checking extension for file: {}", path_str);
-            return path
-                .extension()
-                .and_then(|ext| ext.to_str())
-                .map_or(false, |ext| self.extensions.iter().any(|e| e == ext));
-        }
-
-        // Apply advanced filters. Now OR logic
-        let mut matched_filter = false;
-        for filter in &self.filters {
-            match filter {
-                Filter::FileName(pattern) => {
-                    if let Some(name) = file_name {
-                        if name.contains(pattern) {
-                            matched_filter = true;
-                            break; // If one filter matches, no need to check others for this type
-                        }
-                    }
-                }
-                Filter::PathContains(substring) => {
-                    if path_str.contains(substring) {
-                        matched_filter = true;
-                        break; // If one filter matches, no need to check others for this type
-                    }
-                }
-                Filter::ContentContains(_) => {
-                    matched_filter = true; // Content filter is checked later in process_and_write_file
-                    break;
-                }
-            }
+        // Known binary formats are left out without reading them
+        let binary_extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| UNSUPPORTED_EXTENSIONS.contains(&ext));
+        if binary_extension && !self.forces_text(relative_path) {
+            debug!("Excluding file with a binary extension: {}", path.display());
+            return false;
         }
 
-        if !matched_filter {
+        // Filters on the contents are checked once the file is read
+        if self.filter_matches(&Candidate::new(path, relative_path, Content::Unread)) == Some(false)
+        {
+            debug!(
+                "Excluding file not matching the filters: {}",
+                path.display()
+            );
             return false;
         }
 
-        // If no extensions are specified, process all files that pass the filters
         if self.extensions.is_empty() {
-            debug!("Processing file with filters: {}", path_str);
             return true;
         }
 
         // Match files by extension
         path.extension()
             .and_then(|ext| ext.to_str())
-            .map_or(false, |ext| self.extensions.iter().any(|e| e == ext))
+            .is_some_and(|ext| self.extensions.iter().any(|e| e == ext))
+    }
+
+    /// Asks on `writer` before replacing an output file that holds data other than a previous
+    /// pack, returning [`Error::Cancelled`] unless the answer read from `reader` is yes.
+    ///
+    /// Once confirmed, `run` overwrites the file. Without confirmation it returns
+    /// [`Error::OutputExists`] instead of asking.
+    pub fn confirm_overwrite<R: BufRead, W: Write>(
+        &mut self,
+        reader: &mut R,
+        writer: &mut W,
+    ) -> Result<()> {
+        if self.writes_to_stdout()
+            || self.validate_output_file(self.output.clone(), self.force, reader, writer)?
+        {
+            self.confirmed = true;
+            Ok(())
+        } else {
+            Err(Error::Cancelled)
+        }
+    }
+
+    /// Checks that a pack split into `count` parts can be written, before any part is, and
+    /// returns the parts left from an earlier run with more of them. Like the output file,
+    /// parts are only replaced if they are empty or previous packs, unless forced.
+    fn check_parts(&self, output: &Path, count: usize) -> Result<Vec<PathBuf>> {
+        // A pack that fits in one part is written to the output itself
+        let written = if count > 1 { count } else { 0 };
+        let replaceable = |path: &Path| {
+            if self.validate_output_file(
+                path.display().to_string(),
+                self.force,
+                &mut io::empty(),
+                &mut io::sink(),
+            )? {
+                Ok(())
+            } else {
+                Err(Error::OutputExists(path.to_owned()))
+            }
+        };
+
+        for number in 1..=written {
+            replaceable(&part_path(output, number))?;
+        }
+        let mut stale = Vec::new();
+        for number in written + 1.. {
+            let path = part_path(output, number);
+            if !path.exists() {
+                break;
+            }
+            replaceable(&path)?;
+            stale.push(path);
+        }
+        Ok(stale)
     }
 
     pub fn validate_output_file<R: BufRead, W: Write>(
@@ -228,55 +605,41 @@ impl DirectoryProcessor {
         force: bool,
         reader: &mut R,
         writer: &mut W,
-    ) -> std::io::Result<bool> {
-        let path = std::path::Path::new(&output_path);
+    ) -> Result<bool> {
+        let path = Path::new(&output_path);
 
         if path.exists() {
             if force {
                 return Ok(true);
             }
 
-            let file = File::open(&output_path)?;
-            let mut buf_reader = std::io::BufReader::new(&file);
-            let mut first_line = String::new();
-
-            // Read the first line of the file and handle other files
-            match buf_reader.read_line(&mut first_line) {
-                Ok(bytes_read) => {
-                    if bytes_read > 0
-                        && first_line.contains(
-                            "This is a .txt file representing an entire directory's contents.",
-                        )
-                    {
-                        return Ok(true);
-                    }
-                }
-                Err(err) if err.kind() == std::io::ErrorKind::InvalidData => {
-                    eprintln!("Binary output file detected (Invalid UTF-8).");
-                }
-                Err(err) => {
-                    eprintln!("I/O error while reading output file: {}", err);
-                    return Err(err.into());
-                }
+            // A pack written earlier is replaced without asking, whatever its format.
+            let mut head = Vec::new();
+            File::open(path)
+                .and_then(|file| file.take(PACK_HEAD_LEN).read_to_end(&mut head))
+                .map_err(Error::io(path))?;
+            if is_pack(&String::from_utf8_lossy(&head)) {
+                return Ok(true);
             }
 
             debug!("Opened output file '{}', reading file size.", output_path);
 
             // Check metadata for file size
-            if let Ok(metadata) = std::fs::metadata(&output_path) {
+            if let Ok(metadata) = std::fs::metadata(path) {
                 if metadata.len() > 0 {
+                    // Errors here come from the prompt, not from the output file
+                    let mut input = String::new();
                     write!(
                         writer,
                         "Output file '{}' already exists and contains data. Overwrite? (y/n): ",
                         output_path
-                    )?;
-                    writer.flush()?;
-
-                    let mut input = String::new();
-                    reader.read_line(&mut input)?;
+                    )
+                    .and_then(|_| writer.flush())
+                    .and_then(|_| reader.read_line(&mut input))
+                    .map_err(Error::io(STDOUT))?;
 
                     if !input.trim().eq_ignore_ascii_case("y") {
-                        writeln!(writer, "Operation cancelled.")?;
+                        writeln!(writer, "Operation cancelled.").map_err(Error::io(STDOUT))?;
                         return Ok(false);
                     }
                 }
@@ -285,38 +648,243 @@ impl DirectoryProcessor {
         Ok(true)
     }
 
-    fn process_and_write_file(
+    /// Whether a file matches any of the filters (all files do if there are none), or `None`
+    /// if that depends on contents not read yet.
+    fn filter_matches(&self, file: &Candidate) -> Option<bool> {
+        let mut result = Some(self.filters.is_empty());
+        for filter in &self.filters {
+            match filter.matches(file) {
+                Some(true) => return Some(true),
+                Some(false) => {}
+                None => result = None,
+            }
+        }
+        result
+    }
+
+    /// Whether the file at `relative_path` (relative to the packed directory) matches one of
+    /// the globs of files always read as text.
+    fn forces_text(&self, relative_path: &Path) -> bool {
+        self.force_text.is_match(relative_path)
+            || relative_path
+                .file_name()
+                .is_some_and(|name| self.force_text.is_match(name))
+    }
+
+    /// The encoding the file at `relative_path` (relative to the packed directory) was
+    /// declared to be in, if any.
+    fn encoding_for(&self, relative_path: &Path) -> Option<&'static Encoding> {
+        let name = relative_path.file_name().unwrap_or_default();
+        self.encodings
+            .iter()
+            .find(|(matcher, _)| matcher.is_match(relative_path) || matcher.is_match(name))
+            .map(|(_, encoding)| *encoding)
+    }
+
+    /// The limit a file of `size` bytes is over, if it is skipped without being read.
+    fn skips_size(&self, size: u64) -> Option<FileSizeLimit> {
+        match self.max_file_size {
+            Some((limit @ FileSizeLimit::Bytes(max), TruncateStrategy::Skip))
+                if size > max as u64 =>
+            {
+                Some(limit)
+            }
+            _ => None,
+        }
+    }
+
+    /// Matches the contents of a file that its path does not select or leave out on its own,
+    /// reading it whole unless it is large. Returns `None` if a large file matched, as it
+    /// still has to be read.
+    fn match_contents(&self, path: &Path, directory_path: &Path) -> Result<Option<ReadOutcome>> {
+        let size = fs::metadata(path).map_err(Error::io(path))?.len();
+        if size <= STREAM_THRESHOLD || self.skips_size(size).is_some() {
+            return self.process_file(path, directory_path, false).map(Some);
+        }
+
+        let mut file = File::open(path).map_err(Error::io(path))?;
+        let mut prefix = Vec::with_capacity(binary::SNIFF_LEN);
+        (&mut file)
+            .take(binary::SNIFF_LEN as u64)
+            .read_to_end(&mut prefix)
+            .map_err(Error::io(path))?;
+        let relative_path = path.strip_prefix(directory_path).unwrap_or(path);
+        let encoding = match self.encoding_for(relative_path) {
+            Some(encoding) => encoding,
+            None => {
+                if let Some(kind) =
+                    binary::sniff(&prefix).filter(|_| !self.forces_text(relative_path))
+                {
+                    return Ok(Some(ReadOutcome::Skipped(format!("binary ({})", kind))));
+                }
+                encoding::detect(&prefix)
+            }
+        };
+
+        debug!("Streaming {} to match its contents", path.display());
+        let relative_path = path.strip_prefix(directory_path).unwrap_or(path);
+        let mut scan = ContentScan::new(&self.filters);
+        let mut matched = None;
+        encoding::read_lines(Cursor::new(prefix).chain(file), encoding, |text| {
+            scan.feed(text);
+            matched = self.filter_matches(&Candidate::new(
+                path,
+                relative_path,
+                Content::Scanned(&scan),
+            ));
+            matched.is_none()
+        })
+        .map_err(Error::io(path))?;
+        if matched.is_none() {
+            scan.finish();
+            matched = self.filter_matches(&Candidate::new(
+                path,
+                relative_path,
+                Content::Scanned(&scan),
+            ));
+        }
+
+        Ok((matched == Some(false)).then_some(ReadOutcome::Filtered))
+    }
+
+    /// Reads a file for the pack, leaving it out if its contents do not match the filters
+    /// unless it is already `selected`.
+    fn process_file(
         &self,
         path: &Path,
-        writer: &mut BufWriter<File>,
-        pb: &ProgressBar,
-    ) -> Result<()> {
-        let content = match std::fs::read_to_string(path) {
-            Ok(content) => content,
-            Err(err) => {
-                eprintln!(
-                    "Non-UTF-8 file or read error for {}: {}",
-                    path.display(),
-                    err
-                );
-                return Err(err.into());
+        directory_path: &Path,
+        selected: bool,
+    ) -> Result<ReadOutcome> {
+        if let Some((FileSizeLimit::Bytes(_), TruncateStrategy::Skip)) = self.max_file_size {
+            // Not worth reading
+            let size = fs::metadata(path).map_err(Error::io(path))?.len();
+            if let Some(limit) = self.skips_size(size) {
+                return Ok(ReadOutcome::Skipped(format!("larger than {}", limit)));
+            }
+        }
+
+        // Paths are relative to the packed directory so the pack can be unpacked
+        let relative_path = path.strip_prefix(directory_path).unwrap_or(path);
+        let bytes = fs::read(path).map_err(Error::io(path))?;
+        let size = bytes.len() as u64;
+        let content = if let Some(encoding) = self.encoding_for(relative_path) {
+            encoding::decode_as(&bytes, encoding).ok_or_else(|| {
+                if encoding == UTF_8 {
+                    Error::NonUtf8(path.to_owned())
+                } else {
+                    Error::InvalidEncoding {
+                        path: path.to_owned(),
+                        encoding: encoding.name(),
+                    }
+                }
+            })?
+        } else if self.forces_text(relative_path) {
+            encoding::decode(bytes).0
+        } else {
+            if let Some(kind) = binary::sniff(&bytes) {
+                return Ok(ReadOutcome::Skipped(format!("binary ({})", kind)));
+            }
+            let (content, encoding) = encoding::decode(bytes);
+            if encoding != UTF_8 {
+                if !binary::looks_like_text(&content) {
+                    return Ok(ReadOutcome::Skipped(
+                        "binary (control characters)".to_string(),
+                    ));
+                }
+                debug!("Decoded {} as {}", path.display(), encoding.name());
             }
+            content
         };
 
-        debug!("Writing content for file: {}", path.display());
+        debug!("Reading content for file: {}", path.display());
 
-        // If there are no content filters, write the content
-        if self.filters.is_empty()
-            || self.filters.iter().any(|f| match f {
-                Filter::ContentContains(ref s) => content.contains(s),
-                _ => false,
-            })
+        if selected
+            || self.filter_matches(&Candidate::new(
+                path,
+                relative_path,
+                Content::Text(&content),
+            )) != Some(false)
         {
-            writeln!(writer, "\n--- {} ---", path.display())?;
-            writeln!(writer, "{}", content)?;
-            pb.inc(1);
+            let lines = content.lines().count();
+            let (content, truncated) = match (self.max_file_size, &self.line_numbers) {
+                (Some((limit, strategy)), line_numbers) => {
+                    match truncate::truncate(&content, size, limit, strategy, line_numbers.as_ref())
+                    {
+                        Some(truncated) => truncated,
+                        None => return Ok(ReadOutcome::Skipped(format!("larger than {}", limit))),
+                    }
+                }
+                (None, Some(line_numbers)) => (line_numbers.apply(&content), false),
+                (None, None) => (content, false),
+            };
+            return Ok(ReadOutcome::Packed(PackedFile {
+                size,
+                lines,
+                truncated,
+                number_separator: self
+                    .line_numbers
+                    .as_ref()
+                    .map(|line_numbers| line_numbers.separator.clone()),
+                ..PackedFile::new(relative_path.to_owned(), content)
+            }));
         }
 
-        Ok(())
+        Ok(ReadOutcome::Filtered)
+    }
+}
+
+/// What became of a file read for the pack.
+enum ReadOutcome {
+    Packed(PackedFile),
+    /// Left out by a content filter.
+    Filtered,
+    /// Left out for the given reason, without failing the run.
+    Skipped(String),
+}
+
+/// Describes why a file could not be read, without its path.
+fn skip_reason(err: &Error) -> String {
+    match err {
+        Error::Io { source, .. } | Error::Write(source) => source.to_string(),
+        Error::NonUtf8(_) => "not valid UTF-8".to_string(),
+        Error::InvalidEncoding { encoding, .. } => format!("not valid {}", encoding),
+        _ => err.to_string(),
     }
 }
+
+/// Maps `items` with `f` on a pool of threads, keeping their order, and advances `pb` for
+/// every item done.
+fn par_map<T: Sync, R: Send>(items: &[T], pb: &ProgressBar, f: impl Fn(&T) -> R + Sync) -> Vec<R> {
+    let workers = thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .min(items.len())
+        .max(1);
+    let next = AtomicUsize::new(0);
+
+    let mut done = thread::scope(|scope| {
+        let readers: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(item) = items.get(i) else {
+                            break;
+                        };
+                        done.push((i, f(item)));
+                        pb.inc(1);
+                    }
+                    done
+                })
+            })
+            .collect();
+
+        readers
+            .into_iter()
+            .flat_map(|reader| reader.join().expect("file reader panicked"))
+            .collect::<Vec<_>>()
+    });
+
+    done.sort_by_key(|(i, _)| *i);
+    done.into_iter().map(|(_, result)| result).collect()
+}
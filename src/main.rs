@@ -1,19 +1,32 @@
 use anyhow::Result;
-use clap::Parser;
-use codepack::{DirectoryProcessor, Filter};
+use clap::{Parser, Subcommand};
+use codepack::{
+    BudgetStrategy, DirectoryProcessor, Error, FileSizeLimit, FilterExpr, Format, GitSelection,
+    LineNumbers, PackSummary, SortOrder, SplitLimit, TokenBudget, TokenizerKind, TreeMode,
+    TruncateStrategy, Unpacker, STDOUT,
+};
 use prettytable::{Cell, Row, Table};
-use std::path::Path;
+use std::{
+    io::{self, IsTerminal, Read, Write},
+    path::Path,
+    process::ExitCode,
+};
 
 #[derive(Parser, Debug)]
 #[command(name = "codepack", version)]
 #[command(
     about = "Convert local directory contents into a single text file, useful for processing by an LLM."
 )]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Path to the local directory (first argument)
-    directory_path: String,
+    #[arg(required = true)]
+    directory_path: Option<String>,
 
-    /// Output file path (optional)author
+    /// Output file path (optional), or - for stdout (the default when stdout is piped)
     #[arg(short, long)]
     output: Option<String>,
 
@@ -35,59 +48,310 @@ struct Args {
 
     #[arg(short = 'f', long = "filter", action = clap::ArgAction::Append)]
     filters: Vec<String>,
+
+    /// Output format: text, markdown, xml, json or jsonl
+    #[arg(long, default_value_t = Format::Text)]
+    format: Format,
+
+    /// Tokenizer used to count tokens: o200k, cl100k or estimate (characters / 4). Defaults to
+    /// o200k when --max-tokens, --split-tokens or --top-files need exact counts, and to
+    /// estimate otherwise
+    #[arg(long)]
+    tokenizer: Option<TokenizerKind>,
+
+    /// List the N files costing the most tokens after packing
+    #[arg(long, value_name = "N")]
+    top_files: Option<usize>,
+
+    /// Leave out files until the pack fits in N tokens
+    #[arg(long, value_name = "N")]
+    max_tokens: Option<usize>,
+
+    /// Which files to keep first under --max-tokens: smallest, shallowest or order
+    #[arg(long, default_value_t = BudgetStrategy::Smallest, requires = "max_tokens")]
+    budget_strategy: BudgetStrategy,
+
+    /// Files to keep first under --max-tokens, by glob (e.g. --priority README.md --priority 'src/**')
+    #[arg(long, value_name = "GLOB", requires = "max_tokens")]
+    priority: Vec<String>,
+
+    /// Order of the files in the pack: path, depth-first (files before subdirectories), size
+    /// (smallest first), mtime (newest first) or git-recent (most recently committed first)
+    #[arg(long, default_value_t = SortOrder::Path)]
+    sort: SortOrder,
+
+    /// Files to write before all others, by glob (e.g. --sort-first 'README*' --sort-first Cargo.toml)
+    #[arg(long, value_name = "GLOB")]
+    sort_first: Vec<String>,
+
+    /// Show a directory tree after the preamble: included (the default for a bare --tree)
+    /// lists the packed files, all (as --tree=all) also lists the files left out, marked as such
+    #[arg(long, value_name = "MODE", num_args = 0..=1, require_equals = true, default_value_t = TreeMode::Off, default_missing_value = "included")]
+    tree: TreeMode,
+
+    /// List every file with the line of the pack its contents start on, its line count and size
+    #[arg(long)]
+    toc: bool,
+
+    /// Prefix every line of each file with its line number in the file
+    #[arg(long)]
+    line_numbers: bool,
+
+    /// Right-align line numbers to N columns (as wide as the file's last line number by default)
+    #[arg(long, value_name = "N", requires = "line_numbers")]
+    line_number_width: Option<usize>,
+
+    /// Text written between a line number and its line
+    #[arg(
+        long,
+        value_name = "SEP",
+        default_value = " | ",
+        requires = "line_numbers"
+    )]
+    line_number_separator: String,
+
+    /// Files to read as text even if they look binary, by name or path glob (e.g. --force-text '*.svg')
+    #[arg(long, value_name = "GLOB")]
+    force_text: Vec<String>,
+
+    /// Cut down files larger than SIZE, in bytes (with an optional KB, MB or GB suffix) or
+    /// lines (e.g. 500KB, 2000lines)
+    #[arg(long, value_name = "SIZE")]
+    max_file_size: Option<FileSizeLimit>,
+
+    /// What to do with files over --max-file-size: skip, head (keep the start), head-tail
+    /// (keep the start and end) or signatures (keep only declarations)
+    #[arg(long, value_name = "STRATEGY", default_value_t = TruncateStrategy::Skip, requires = "max_file_size")]
+    truncate: TruncateStrategy,
+
+    /// Read files matching GLOB in encoding LABEL instead of detecting it
+    /// (e.g. --encoding 'legacy/**=windows-1252' --encoding '*.txt=utf-16le')
+    #[arg(long, value_name = "GLOB=LABEL", value_parser = parse_encoding)]
+    encoding: Vec<(String, String)>,
+
+    /// Fail before writing anything if a file cannot be read, instead of skipping it
+    #[arg(long)]
+    strict: bool,
+
+    /// Pack only files changed in the working tree since REF (HEAD if omitted), including
+    /// untracked files (e.g. --git-changed or --git-changed=main)
+    #[arg(long, value_name = "REF", num_args = 0..=1, require_equals = true, default_missing_value = "HEAD")]
+    git_changed: Option<String>,
+
+    /// Pack only files with changes staged in the git index
+    #[arg(long)]
+    git_staged: bool,
+
+    /// Pack only files changed in a git commit range (e.g. --git-range main..HEAD)
+    #[arg(long, value_name = "A..B")]
+    git_range: Option<String>,
+
+    /// Split the pack into numbered parts of at most N tokens each
+    #[arg(long, value_name = "N", conflicts_with = "split_bytes")]
+    split_tokens: Option<usize>,
+
+    /// Split the pack into numbered parts of at most N bytes each
+    #[arg(long, value_name = "N")]
+    split_bytes: Option<usize>,
 }
 
-fn main() -> Result<()> {
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Recreate the files contained in a pack below a destination directory
+    Unpack {
+        /// Path to the pack file, or - to read it from stdin
+        pack: String,
+
+        /// Directory to write the files into
+        destination: String,
+
+        /// Leave out files truncated by --max-file-size instead of refusing the pack
+        #[arg(long)]
+        skip_truncated: bool,
+    },
+}
+
+/// Prints what the library reports, such as skipped files, to stderr.
+struct StderrLogger;
+
+impl log::Log for StderrLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::max_level() && metadata.target().starts_with("codepack")
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.enabled(record.metadata()) {
+            eprintln!(
+                "{}: {}",
+                record.level().as_str().to_lowercase(),
+                record.args()
+            );
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: StderrLogger = StderrLogger;
+
+fn parse_encoding(s: &str) -> Result<(String, String), String> {
+    // Labels never contain '=', globs rarely do
+    s.rsplit_once('=')
+        .map(|(glob, label)| (glob.to_string(), label.to_string()))
+        .ok_or_else(|| format!("expected GLOB=LABEL, got '{}'", s))
+}
+
+fn main() -> ExitCode {
+    // Only fails if a logger is already set
+    let _ = log::set_logger(&LOGGER);
     log::set_max_level(log::LevelFilter::Info);
-    let mut args = Args::parse();
-    let directory_path = Path::new(&args.directory_path);
 
-    if !args.output.is_some() {
-        args.output = Some({
+    match run(Args::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            let error = err.downcast_ref::<Error>();
+            // The prompt already said so
+            if !matches!(error, Some(Error::Cancelled)) {
+                eprintln!("Error: {:#}", err);
+            }
+            ExitCode::from(error.map_or(1, exit_code))
+        }
+    }
+}
+
+/// Exit codes of the CLI, one per kind of library error (2 matches clap's usage errors).
+fn exit_code(error: &Error) -> u8 {
+    match error {
+        Error::InvalidGlob { .. }
+        | Error::InvalidFilter(_)
+        | Error::InvalidFilterValue { .. }
+        | Error::InvalidRegex { .. }
+        | Error::FilterSyntax { .. }
+        | Error::InvalidOptions(_) => 2,
+        Error::OutputExists(_) => 3,
+        Error::Cancelled => 4,
+        Error::Io { .. } | Error::Write(_) => 5,
+        Error::NonUtf8(_) | Error::InvalidEncoding { .. } => 6,
+        Error::InvalidPack(_) | Error::UnsafePath(_) => 7,
+        Error::Git(_) => 8,
+    }
+}
+
+fn run(mut args: Args) -> Result<()> {
+    if let Some(Command::Unpack {
+        pack,
+        destination,
+        skip_truncated,
+    }) = &args.command
+    {
+        return unpack(Path::new(pack), Path::new(destination), *skip_truncated);
+    }
+
+    let directory_path = args.directory_path.take().unwrap();
+    let directory_path = Path::new(&directory_path);
+
+    if args.output.is_none() {
+        args.output = Some(if io::stdout().is_terminal() {
             let directory_name = directory_path
                 .file_name()
                 .and_then(|n| n.to_str())
                 .unwrap_or("directory");
 
             // Use the number of files processed to build the description
-            format!("{}_code_pack.txt", directory_name)
+            format!("{}_code_pack.{}", directory_name, args.format.extension())
+        } else {
+            // Piped into another program, so hand it the pack directly
+            STDOUT.to_string()
         });
     }
     let filters = args
         .filters
         .iter()
-        .map(|filter| {
-            if let Some(value) = filter.strip_prefix("file.name=") {
-                Filter::FileName(value.to_string())
-            } else if let Some(value) = filter.strip_prefix("path.contains=") {
-                Filter::PathContains(value.to_string())
-            } else if let Some(value) = filter.strip_prefix("content.contains=") {
-                Filter::ContentContains(value.to_string())
-            } else {
-                panic!("Invalid filter: {}", filter);
-            }
-        })
-        .collect();
-
-    let processor = DirectoryProcessor::new(
-        args.extensions,
-        args.excluded_files,
-        args.suppress_prompt,
-        args.output.clone().unwrap(),
-        args.force,
-        filters,
-    );
+        .map(|filter| filter.parse())
+        .collect::<Result<Vec<FilterExpr>, _>>()?;
+
+    let exact =
+        args.max_tokens.is_some() || args.split_tokens.is_some() || args.top_files.is_some();
+    let tokenizer = args.tokenizer.unwrap_or(if exact {
+        TokenizerKind::O200k
+    } else {
+        TokenizerKind::Estimate
+    });
+
+    let mut builder = DirectoryProcessor::builder()
+        .extensions(args.extensions)
+        .excluded_files(args.excluded_files)
+        .suppress_prompt(args.suppress_prompt)
+        .output(args.output.clone().unwrap())
+        .force(args.force)
+        .filters(filters)
+        .format(args.format)
+        .tokenizer(tokenizer)
+        .sort(args.sort)
+        .sort_first(args.sort_first)
+        .tree(args.tree)
+        .toc(args.toc)
+        .force_text(args.force_text)
+        .strict(args.strict);
+    if let Some(limit) = args.max_file_size {
+        builder = builder.max_file_size(limit, args.truncate);
+    }
+    for (glob, label) in args.encoding {
+        builder = builder.encoding(glob, label);
+    }
+    if let Some(reference) = args.git_changed {
+        builder = builder.git(GitSelection::Changed(reference));
+    }
+    if args.git_staged {
+        builder = builder.git(GitSelection::Staged);
+    }
+    if let Some(range) = args.git_range {
+        builder = builder.git(GitSelection::Range(range));
+    }
+
+    if let Some(max_tokens) = args.max_tokens {
+        let budget = TokenBudget::new(max_tokens)
+            .with_strategy(args.budget_strategy)
+            .with_priority(&args.priority)?;
+        builder = builder.token_budget(budget);
+    }
+
+    if args.line_numbers {
+        builder = builder.line_numbers(LineNumbers {
+            width: args.line_number_width,
+            separator: args.line_number_separator,
+        });
+    }
+
+    if let Some(tokens) = args.split_tokens {
+        builder = builder.split(SplitLimit::Tokens(tokens));
+    } else if let Some(bytes) = args.split_bytes {
+        builder = builder.split(SplitLimit::Bytes(bytes));
+    }
+
+    let mut processor = builder.build()?;
+    // Nobody can answer the prompt without a terminal, so `run` refuses instead
+    if io::stdin().is_terminal() {
+        processor.confirm_overwrite(&mut io::stdin().lock(), &mut io::stdout())?;
+    }
 
     // Start the timer
     let start_time = std::time::Instant::now();
 
     // Run the processing
-    let files = processor.run(directory_path)?;
+    let summary = processor.run(directory_path)?;
 
     // Calculate elapsed time
     let duration = start_time.elapsed();
     let formatted_time = format!("{:?}", duration);
 
+    // Keep the summary out of the pack when the pack goes to stdout
+    let mut report: Box<dyn Write> = if processor.writes_to_stdout() {
+        Box::new(io::stderr())
+    } else {
+        Box::new(io::stdout())
+    };
+
     // Output the stats and details in a pretty table
     let mut table = Table::new();
     table.add_row(Row::new(vec![
@@ -96,11 +360,131 @@ fn main() -> Result<()> {
     ]));
     table.add_row(Row::new(vec![
         Cell::new("Number of files processed"),
-        Cell::new(&files.to_string()),
+        Cell::new(&summary.files.len().to_string()),
+    ]));
+    table.add_row(Row::new(vec![
+        Cell::new(&format!("Total tokens ({})", tokenizer)),
+        Cell::new(&summary.tokens.to_string()),
+    ]));
+    if args.max_tokens.is_some() {
+        table.add_row(Row::new(vec![
+            Cell::new("Files omitted to fit the token budget"),
+            Cell::new(&summary.omitted.len().to_string()),
+        ]));
+    }
+    if !summary.skipped.is_empty() {
+        table.add_row(Row::new(vec![
+            Cell::new("Files skipped"),
+            Cell::new(&summary.skipped.len().to_string()),
+        ]));
+    }
+    table.print(&mut report)?;
+
+    if !summary.skipped.is_empty() {
+        print_skipped_files(&mut report, &summary)?;
+    }
+
+    if !summary.omitted.is_empty() {
+        print_omitted_files(&mut report, &summary)?;
+    }
+
+    if let Some(n) = args.top_files {
+        print_largest_files(&mut report, &summary, n)?;
+    }
+
+    if processor.writes_to_stdout() {
+        writeln!(report, "\nOutput written to stdout")?;
+    } else if summary.outputs.len() > 1 {
+        writeln!(
+            report,
+            "\nOutput written to {} parts:",
+            summary.outputs.len()
+        )?;
+        for output in &summary.outputs {
+            writeln!(report, "  {:?}", output)?;
+        }
+    } else {
+        writeln!(report, "\nOutput written to: {:?}", args.output.unwrap())?;
+    }
+
+    Ok(())
+}
+
+fn print_largest_files(report: &mut dyn Write, summary: &PackSummary, n: usize) -> Result<()> {
+    let mut table = Table::new();
+    table.set_titles(Row::new(vec![
+        Cell::new("File"),
+        Cell::new("Tokens"),
+        Cell::new("Share of pack"),
+    ]));
+    for file in summary.largest_files(n) {
+        let share = file.tokens as f64 * 100.0 / summary.tokens.max(1) as f64;
+        table.add_row(Row::new(vec![
+            Cell::new(&file.path.display().to_string()),
+            Cell::new(&file.tokens.to_string()),
+            Cell::new(&format!("{:.1}%", share)),
+        ]));
+    }
+
+    writeln!(report, "\nLargest files by tokens:")?;
+    table.print(report)?;
+    Ok(())
+}
+
+fn print_omitted_files(report: &mut dyn Write, summary: &PackSummary) -> Result<()> {
+    let mut table = Table::new();
+    table.set_titles(Row::new(vec![Cell::new("File"), Cell::new("Tokens")]));
+    for file in &summary.omitted {
+        table.add_row(Row::new(vec![
+            Cell::new(&file.path.display().to_string()),
+            Cell::new(&file.tokens.to_string()),
+        ]));
+    }
+
+    writeln!(report, "\nOmitted to fit the token budget:")?;
+    table.print(report)?;
+    Ok(())
+}
+
+fn print_skipped_files(report: &mut dyn Write, summary: &PackSummary) -> Result<()> {
+    let mut table = Table::new();
+    table.set_titles(Row::new(vec![Cell::new("File"), Cell::new("Reason")]));
+    for file in &summary.skipped {
+        table.add_row(Row::new(vec![
+            Cell::new(&file.path.display().to_string()),
+            Cell::new(&file.reason),
+        ]));
+    }
+
+    writeln!(report, "\nSkipped:")?;
+    table.print(report)?;
+    Ok(())
+}
+
+fn unpack(pack: &Path, destination: &Path, skip_truncated: bool) -> Result<()> {
+    let start_time = std::time::Instant::now();
+    let unpacker = Unpacker::new(destination).skip_truncated(skip_truncated);
+    let files = if pack == Path::new(STDOUT) {
+        let mut content = String::new();
+        io::stdin().read_to_string(&mut content)?;
+        unpacker.unpack(&content)?
+    } else {
+        unpacker.unpack_file(pack)?
+    };
+    let duration = start_time.elapsed();
+
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("Total time taken"),
+        Cell::new(&format!("{:?}", duration)),
+    ]));
+    table.add_row(Row::new(vec![
+        Cell::new("Number of files unpacked"),
+        Cell::new(&files.len().to_string()),
     ]));
     table.printstd();
 
-    println!("\nOutput written to: {:?}", args.output.unwrap());
+    println!("\nFiles written to: {:?}", destination);
 
     Ok(())
 }
@@ -0,0 +1,198 @@
+use std::{
+    fmt,
+    str::FromStr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// How a file's size, line count or modification time is compared to the value of a filter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Less,
+    LessOrEqual,
+    Equal,
+    GreaterOrEqual,
+    Greater,
+}
+
+impl Comparison {
+    /// Every operator, with the two-character ones first so they are split off whole.
+    const OPERATORS: [(&'static str, Comparison); 5] = [
+        ("<=", Comparison::LessOrEqual),
+        (">=", Comparison::GreaterOrEqual),
+        ("<", Comparison::Less),
+        (">", Comparison::Greater),
+        ("=", Comparison::Equal),
+    ];
+
+    /// Splits the operator off the start of `s`, returning it with the rest.
+    pub(crate) fn split(s: &str) -> Option<(Comparison, &str)> {
+        Self::OPERATORS
+            .iter()
+            .find_map(|(operator, op)| s.strip_prefix(operator).map(|rest| (*op, rest)))
+    }
+
+    pub fn as_str(self) -> &'static str {
+        Self::OPERATORS
+            .iter()
+            .find(|(_, op)| *op == self)
+            .map_or("", |(operator, _)| operator)
+    }
+
+    /// Whether `value` compares to `bound` this way.
+    pub fn holds<T: PartialOrd>(self, value: T, bound: T) -> bool {
+        match self {
+            Comparison::Less => value < bound,
+            Comparison::LessOrEqual => value <= bound,
+            Comparison::Equal => value == bound,
+            Comparison::GreaterOrEqual => value >= bound,
+            Comparison::Greater => value > bound,
+        }
+    }
+
+    /// Whether a count that will end up at least `at_least` compares to `bound` this way, or
+    /// `None` if that depends on how much higher it gets.
+    pub(crate) fn holds_at_least(self, at_least: usize, bound: usize) -> Option<bool> {
+        match self {
+            Comparison::Less if at_least >= bound => Some(false),
+            Comparison::LessOrEqual | Comparison::Equal if at_least > bound => Some(false),
+            Comparison::Greater if at_least > bound => Some(true),
+            Comparison::GreaterOrEqual if at_least >= bound => Some(true),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A time the modification time of a file is compared to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Timestamp {
+    /// A whole day in UTC, e.g. `2026-09-01`: `modified>2026-09-01` selects files modified
+    /// after that day, and `modified=2026-09-01` files modified during it.
+    Date { year: i32, month: u32, day: u32 },
+    /// An age, e.g. `7d`: `modified<7d` selects files modified less than 7 days ago.
+    Ago(Duration),
+}
+
+/// Units of [`Timestamp::Ago`], largest first.
+const AGE_UNITS: &[(&str, u64)] = &[
+    ("w", 7 * 24 * 60 * 60),
+    ("d", 24 * 60 * 60),
+    ("h", 60 * 60),
+    ("m", 60),
+    ("s", 1),
+];
+
+impl Timestamp {
+    /// Whether a file modified at `modified` compares to this time with `op`, when it is
+    /// now `now`.
+    pub(crate) fn compare(self, op: Comparison, modified: SystemTime, now: SystemTime) -> bool {
+        match self {
+            Timestamp::Date { year, month, day } => {
+                let start = days_from_civil(year, month, day) * SECONDS_PER_DAY;
+                let end = start + SECONDS_PER_DAY;
+                let modified = unix_seconds(modified);
+                match op {
+                    Comparison::Less => modified < start,
+                    Comparison::LessOrEqual => modified < end,
+                    Comparison::Equal => (start..end).contains(&modified),
+                    Comparison::GreaterOrEqual => modified >= start,
+                    Comparison::Greater => modified >= end,
+                }
+            }
+            // Files modified in the future are brand new
+            Timestamp::Ago(age) => op.holds(now.duration_since(modified).unwrap_or_default(), age),
+        }
+    }
+}
+
+impl FromStr for Timestamp {
+    type Err = String;
+
+    /// Parses a day as `YYYY-MM-DD`, or an age as a number followed by `s`, `m`, `h`, `d` or
+    /// `w`, e.g. `2026-09-01` or `7d`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid time '{}' (e.g. 2026-09-01 or 7d)", s);
+
+        if let [year, month, day] = s.split('-').collect::<Vec<_>>()[..] {
+            let [year, month, day] = [year, month, day].map(|part| part.parse::<u32>().ok());
+            let (Some(year), Some(month), Some(day)) = (year, month, day) else {
+                return Err(invalid());
+            };
+            if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year as i32, month) {
+                return Err(format!("invalid date '{}'", s));
+            }
+            return Ok(Timestamp::Date {
+                year: year as i32,
+                month,
+                day,
+            });
+        }
+
+        let digits = s.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+        let count: u64 = digits.parse().map_err(|_| invalid())?;
+        let unit = match &s[digits.len()..] {
+            "min" => "m",
+            unit => unit,
+        };
+        let (_, seconds) = AGE_UNITS
+            .iter()
+            .find(|(name, _)| *name == unit)
+            .ok_or_else(invalid)?;
+        Ok(Timestamp::Ago(Duration::from_secs(
+            count.saturating_mul(*seconds),
+        )))
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Timestamp::Date { year, month, day } => {
+                write!(f, "{:04}-{:02}-{:02}", year, month, day)
+            }
+            Timestamp::Ago(age) => {
+                let seconds = age.as_secs();
+                let (unit, size) = AGE_UNITS
+                    .iter()
+                    .find(|(_, size)| seconds % size == 0)
+                    .unwrap_or(&("s", 1));
+                write!(f, "{}{}", seconds / size, unit)
+            }
+        }
+    }
+}
+
+/// Seconds since the Unix epoch, negative before it.
+fn unix_seconds(time: SystemTime) -> i64 {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(after) => after.as_secs() as i64,
+        Err(before) => -(before.duration().as_secs() as i64),
+    }
+}
+
+/// Days from the Unix epoch to a day of the proleptic Gregorian calendar.
+fn days_from_civil(year: i32, month: u32, day: u32) -> i64 {
+    // Count from March, so the leap day ends the year
+    let year = i64::from(year) - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * i64::from((month + 9) % 12) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
@@ -0,0 +1,140 @@
+use crate::{
+    file::PackedFile,
+    format::{OmittedFile, OutputFormat, PackInfo},
+    tokens::Tokenizer,
+};
+use std::{
+    io::{self, Write},
+    path::PathBuf,
+};
+
+/// Statistics about a written pack.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PackSummary {
+    /// Files written to the pack, in the order they appear.
+    pub files: Vec<FileSummary>,
+    /// Tokens of the whole pack, including the preamble and delimiters.
+    pub tokens: usize,
+    /// Files left out to fit the token budget.
+    pub omitted: Vec<OmittedFile>,
+    /// Files left out because they could not be read as text, in path order.
+    pub skipped: Vec<SkippedFile>,
+    /// Files the pack was written to, more than one if it was split into parts.
+    pub outputs: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileSummary {
+    pub path: PathBuf,
+    pub size: u64,
+    /// Tokens the file costs in the pack, including its delimiter.
+    pub tokens: usize,
+}
+
+/// A file found by the walk but left out because it could not be packed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkippedFile {
+    /// Relative to the packed directory.
+    pub path: PathBuf,
+    pub reason: String,
+}
+
+impl PackSummary {
+    /// Adds the files and tokens of another part of the same pack.
+    pub(crate) fn merge(&mut self, other: PackSummary) {
+        self.files.extend(other.files);
+        self.tokens += other.tokens;
+        self.outputs.extend(other.outputs);
+    }
+
+    /// Returns up to `n` files, most tokens first.
+    pub fn largest_files(&self, n: usize) -> Vec<&FileSummary> {
+        let mut files: Vec<&FileSummary> = self.files.iter().collect();
+        files.sort_by(|a, b| b.tokens.cmp(&a.tokens).then_with(|| a.path.cmp(&b.path)));
+        files.truncate(n);
+        files
+    }
+}
+
+/// Tokens of a section holding `file`: those of its contents, counted when it was read,
+/// plus those of the text around them. Contents escaped by the format, as in JSON, cannot
+/// be found in the section, so the text around them is estimated.
+pub(crate) fn section_tokens(section: &str, file: &PackedFile, tokenizer: &dyn Tokenizer) -> usize {
+    let Some(tokens) = file.tokens else {
+        return tokenizer.count(section);
+    };
+    match section.find(file.content.as_str()) {
+        Some(start) => {
+            tokens
+                + tokenizer.count(&section[..start])
+                + tokenizer.count(&section[start + file.content.len()..])
+        }
+        None => tokens + section.len().saturating_sub(file.content.len()).div_ceil(4),
+    }
+}
+
+/// Lays out a pack with a formatter, counting the tokens of every section it writes.
+pub(crate) struct PackWriter<W: Write> {
+    writer: W,
+    formatter: Box<dyn OutputFormat>,
+    tokenizer: Box<dyn Tokenizer>,
+    section: Vec<u8>,
+    summary: PackSummary,
+}
+
+impl<W: Write> PackWriter<W> {
+    pub fn new(writer: W, formatter: Box<dyn OutputFormat>, tokenizer: Box<dyn Tokenizer>) -> Self {
+        Self {
+            writer,
+            formatter,
+            tokenizer,
+            section: Vec::new(),
+            summary: PackSummary::default(),
+        }
+    }
+
+    pub fn header(&mut self, info: &PackInfo) -> io::Result<()> {
+        self.section.clear();
+        self.formatter.write_header(&mut self.section, info)?;
+        self.flush_section().map(drop)
+    }
+
+    pub fn file(&mut self, file: &PackedFile) -> io::Result<()> {
+        self.section.clear();
+        self.formatter.write_file(&mut self.section, file)?;
+        self.writer.write_all(&self.section)?;
+        let tokens = section_tokens(
+            &String::from_utf8_lossy(&self.section),
+            file,
+            self.tokenizer.as_ref(),
+        );
+        self.summary.tokens += tokens;
+
+        self.summary.files.push(FileSummary {
+            path: file.path.clone(),
+            size: file.size,
+            tokens,
+        });
+        Ok(())
+    }
+
+    pub fn finish(mut self, info: &PackInfo) -> io::Result<PackSummary> {
+        self.section.clear();
+        self.formatter.write_footer(&mut self.section, info)?;
+        self.flush_section()?;
+        self.writer.flush()?;
+
+        self.summary.omitted = info.omitted.clone();
+        Ok(self.summary)
+    }
+
+    /// Writes out the current section, returning its token count.
+    fn flush_section(&mut self) -> io::Result<usize> {
+        self.writer.write_all(&self.section)?;
+        let tokens = self
+            .tokenizer
+            .count(&String::from_utf8_lossy(&self.section));
+        self.summary.tokens += tokens;
+        Ok(tokens)
+    }
+}
@@ -0,0 +1,128 @@
+use crate::git;
+use globset::GlobSet;
+use log::warn;
+use std::{
+    cmp::Reverse,
+    collections::HashMap,
+    fmt, fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::SystemTime,
+};
+
+/// The order files are written in. Ties are always broken by path, so the same files give
+/// the same pack.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SortOrder {
+    /// By path, component by component.
+    #[default]
+    Path,
+    /// By directory, with the files of a directory before its subdirectories.
+    DepthFirst,
+    /// Smallest files first.
+    Size,
+    /// Most recently modified files first.
+    Modified,
+    /// Files most recently changed in git first, then files without history.
+    GitRecent,
+}
+
+impl FromStr for SortOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "path" => Ok(SortOrder::Path),
+            "depth-first" | "depth" => Ok(SortOrder::DepthFirst),
+            "size" => Ok(SortOrder::Size),
+            "mtime" | "modified" => Ok(SortOrder::Modified),
+            "git-recent" | "git" => Ok(SortOrder::GitRecent),
+            _ => Err(format!(
+                "unknown sort order '{}' (expected one of: path, depth-first, size, mtime, git-recent)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for SortOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SortOrder::Path => "path",
+            SortOrder::DepthFirst => "depth-first",
+            SortOrder::Size => "size",
+            SortOrder::Modified => "mtime",
+            SortOrder::GitRecent => "git-recent",
+        })
+    }
+}
+
+/// Sorts paths found below `directory_path`, putting those matching `first` (earlier globs
+/// first, matched against the relative path) before all others.
+pub(crate) fn sort(
+    paths: &mut [PathBuf],
+    order: SortOrder,
+    first: &GlobSet,
+    directory_path: &Path,
+) {
+    let relative = |path: &Path| path.strip_prefix(directory_path).unwrap_or(path).to_owned();
+    let rank = |path: &Path| {
+        first
+            .matches(relative(path))
+            .into_iter()
+            .min()
+            .unwrap_or(usize::MAX)
+    };
+
+    // The walk returns paths sorted, so a stable sort leaves ties in path order.
+    match order {
+        SortOrder::Path => paths.sort_by_cached_key(|path| rank(path)),
+        SortOrder::DepthFirst => paths.sort_by_cached_key(|path| {
+            // A file sorts before the directories next to it
+            let mut key: Vec<_> = relative(path)
+                .components()
+                .map(|component| (true, component.as_os_str().to_owned()))
+                .collect();
+            if let Some(name) = key.last_mut() {
+                name.0 = false;
+            }
+            (rank(path), key)
+        }),
+        SortOrder::Size => {
+            paths.sort_by_cached_key(|path| (rank(path), fs::metadata(path).map_or(0, |m| m.len())))
+        }
+        SortOrder::Modified => paths.sort_by_cached_key(|path| {
+            let modified = fs::metadata(path).and_then(|m| m.modified());
+            (
+                rank(path),
+                Reverse(modified.unwrap_or(SystemTime::UNIX_EPOCH)),
+            )
+        }),
+        SortOrder::GitRecent => {
+            let recent = git_recent(directory_path);
+            paths.sort_by_cached_key(|path| {
+                let position = recent.get(&relative(path)).copied();
+                (rank(path), position.unwrap_or(usize::MAX))
+            });
+        }
+    }
+}
+
+/// Maps the files changed in the history of `directory_path`, relative to it, to how many
+/// other files were changed more recently.
+fn git_recent(directory_path: &Path) -> HashMap<PathBuf, usize> {
+    let paths = match git::history(directory_path) {
+        Ok(paths) => paths,
+        Err(err) => {
+            warn!("Not sorting by git history: {}", err);
+            return HashMap::new();
+        }
+    };
+
+    let mut recent = HashMap::new();
+    for path in paths {
+        let position = recent.len();
+        recent.entry(path).or_insert(position);
+    }
+    recent
+}
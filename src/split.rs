@@ -0,0 +1,251 @@
+use crate::{
+    error::{Error, Result},
+    file::PackedFile,
+    format::{FormatterFactory, OutputFormat, PackInfo, PartInfo},
+    pack::section_tokens,
+    toc::TocEntry,
+    tokens::Tokenizer,
+};
+use std::{
+    fmt, io,
+    path::{Path, PathBuf},
+};
+
+/// The most a single part of a split pack may hold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitLimit {
+    Tokens(usize),
+    Bytes(usize),
+}
+
+impl SplitLimit {
+    fn max(self) -> usize {
+        match self {
+            SplitLimit::Tokens(max) | SplitLimit::Bytes(max) => max,
+        }
+    }
+}
+
+impl fmt::Display for SplitLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SplitLimit::Tokens(max) => write!(f, "{} tokens", max),
+            SplitLimit::Bytes(max) => write!(f, "{} bytes", max),
+        }
+    }
+}
+
+/// Measures rendered sections in the unit of the limit.
+struct Measure<'a> {
+    limit: SplitLimit,
+    tokenizer: &'a dyn Tokenizer,
+    formatter: Box<dyn OutputFormat>,
+    section: Vec<u8>,
+    /// Line given to the entries of a part's table of contents, if parts have one.
+    toc_line: Option<usize>,
+}
+
+impl Measure<'_> {
+    fn text(&self, text: &str) -> usize {
+        match self.limit {
+            SplitLimit::Tokens(_) => self.tokenizer.count(text),
+            SplitLimit::Bytes(_) => text.len(),
+        }
+    }
+
+    fn section(&mut self) -> usize {
+        let section = std::mem::take(&mut self.section);
+        let cost = self.text(&String::from_utf8_lossy(&section));
+        self.section = section;
+        cost
+    }
+
+    /// What a file costs in a part: its section plus its lines in the part's contents and
+    /// table of contents.
+    fn file(&mut self, file: &PackedFile) -> io::Result<usize> {
+        let toc = self.toc(file, 2)?.saturating_sub(self.toc(file, 1)?);
+        self.section.clear();
+        self.formatter.write_file(&mut self.section, file)?;
+        let section = match self.limit {
+            SplitLimit::Tokens(_) => section_tokens(
+                &String::from_utf8_lossy(&self.section),
+                file,
+                self.tokenizer,
+            ),
+            SplitLimit::Bytes(_) => self.section.len(),
+        };
+        Ok(section + toc + self.text(&format!("- {}\n", file.label())))
+    }
+
+    /// What the table of contents of a part costs besides its entries.
+    fn toc_title(&mut self) -> io::Result<usize> {
+        let empty = PackedFile::new(PathBuf::new(), String::new());
+        let one = self.toc(&empty, 1)?;
+        let entry = self.toc(&empty, 2)?.saturating_sub(one);
+        Ok(one.saturating_sub(entry))
+    }
+
+    /// Measures a header with nothing but a table of contents listing `file` `count` times.
+    fn toc(&mut self, file: &PackedFile, count: usize) -> io::Result<usize> {
+        let Some(line) = self.toc_line else {
+            return Ok(0);
+        };
+        let info = PackInfo {
+            toc: vec![TocEntry::new(file, line); count],
+            ..Default::default()
+        };
+        self.section.clear();
+        self.formatter.write_header(&mut self.section, &info)?;
+        Ok(self.section())
+    }
+
+    fn overhead(&mut self, info: &PackInfo) -> io::Result<usize> {
+        self.section.clear();
+        self.formatter.write_header(&mut self.section, info)?;
+        self.formatter.write_footer(&mut self.section, info)?;
+        Ok(self.section())
+    }
+}
+
+/// Groups files into parts that each fit in the limit, keeping their order.
+///
+/// A file is only split, at line boundaries, when it does not fit in a part on its own.
+/// Fails with [`Error::InvalidOptions`] if the limit is too small for the preamble of a
+/// part, or for a line of a file; formatting errors are reported on `output`.
+pub(crate) fn split(
+    files: Vec<PackedFile>,
+    limit: SplitLimit,
+    formatters: &FormatterFactory,
+    tokenizer: &dyn Tokenizer,
+    info: &PackInfo,
+    output: &Path,
+) -> Result<Vec<Vec<PackedFile>>> {
+    let formatter = formatters.formatter();
+    let mut measure = Measure {
+        limit,
+        tokenizer,
+        // Entries share the widest line number the pack can have
+        toc_line: info
+            .toc
+            .first()
+            .filter(|_| formatter.content_offset().is_some())
+            .map(|entry| entry.line),
+        formatter,
+        section: Vec::new(),
+    };
+
+    // Reserve room for the preamble and trailer of a part, with generous part numbers. Each
+    // part lists only its own files in its table of contents, so their lines are charged to
+    // the files, leaving the title of the table.
+    let mut probe = info.clone();
+    probe.part = Some(PartInfo {
+        number: 9999,
+        total: 9999,
+        contents: Vec::new(),
+    });
+    probe.toc.clear();
+    let overhead = measure.overhead(&probe).map_err(Error::io(output))?
+        + measure.toc_title().map_err(Error::io(output))?;
+    if overhead >= limit.max() {
+        return Err(Error::InvalidOptions(format!(
+            "parts of at most {} cannot hold anything after the preamble of a part, which takes {}",
+            limit, overhead
+        )));
+    }
+    let available = limit.max() - overhead;
+
+    let mut parts = Vec::new();
+    let mut current = Vec::new();
+    let mut used = 0;
+
+    for file in files {
+        let cost = measure.file(&file).map_err(Error::io(output))?;
+        let pieces = if cost > available {
+            split_file(&file, available, &mut measure).map_err(Error::io(output))?
+        } else {
+            vec![(file, cost)]
+        };
+
+        for (piece, cost) in pieces {
+            // Only a single line can be larger than a part
+            if cost > available {
+                return Err(Error::InvalidOptions(format!(
+                    "{} does not fit in parts of at most {}, even on its own",
+                    piece.label(),
+                    limit
+                )));
+            }
+            if used + cost > available && !current.is_empty() {
+                parts.push(std::mem::take(&mut current));
+                used = 0;
+            }
+            used += cost;
+            current.push(piece);
+        }
+    }
+
+    if !current.is_empty() || parts.is_empty() {
+        parts.push(current);
+    }
+    Ok(parts)
+}
+
+/// Splits a file into pieces of consecutive lines that each fit in `available`.
+///
+/// A single line longer than `available` still gets a piece of its own.
+fn split_file(
+    file: &PackedFile,
+    available: usize,
+    measure: &mut Measure,
+) -> io::Result<Vec<(PackedFile, usize)>> {
+    let lines: Vec<&str> = file.content.split_inclusive('\n').collect();
+    if lines.len() < 2 {
+        let cost = measure.file(file)?;
+        return Ok(vec![(file.clone(), cost)]);
+    }
+
+    // Estimate with the cost of an empty piece plus its lines, then check the real cost.
+    let widest = file.chunk(lines.len(), &[""]);
+    let budget = available.saturating_sub(measure.file(&widest)?);
+
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    while start < lines.len() {
+        let mut end = start;
+        let mut used = 0;
+        while end < lines.len() {
+            let cost = measure.text(lines[end]);
+            if end > start && used + cost > budget {
+                break;
+            }
+            used += cost;
+            end += 1;
+        }
+
+        loop {
+            let piece = file.chunk(start + 1, &lines[start..end]);
+            let cost = measure.file(&piece)?;
+            if cost <= available || end == start + 1 {
+                pieces.push((piece, cost));
+                break;
+            }
+            end -= 1;
+        }
+        start = end;
+    }
+
+    Ok(pieces)
+}
+
+/// Returns the path of a part, e.g. `name_code_pack.part2.txt` for `name_code_pack.txt`.
+pub fn part_path(output: &Path, number: usize) -> PathBuf {
+    let stem = output
+        .file_stem()
+        .map(|stem| stem.to_string_lossy())
+        .unwrap_or_default();
+    let name = match output.extension() {
+        Some(ext) => format!("{}.part{}.{}", stem, number, ext.to_string_lossy()),
+        None => format!("{}.part{}", stem, number),
+    };
+    output.with_file_name(name)
+}
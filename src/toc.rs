@@ -0,0 +1,77 @@
+use crate::{
+    file::PackedFile,
+    format::{FormatterFactory, PackInfo},
+};
+use std::{io, path::PathBuf};
+
+/// A file listed in the table of contents at the top of a pack.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TocEntry {
+    pub path: PathBuf,
+    /// The path, followed by the lines held if this is a piece of a larger file.
+    pub label: String,
+    /// Line of the pack holding the first line of the file's contents, starting at 1.
+    pub line: usize,
+    pub lines: usize,
+    /// Size of the contents in bytes.
+    pub size: u64,
+}
+
+impl TocEntry {
+    pub(crate) fn new(file: &PackedFile, line: usize) -> Self {
+        Self {
+            path: file.path.clone(),
+            label: file.label(),
+            line,
+            lines: file.lines,
+            size: file.size,
+        }
+    }
+}
+
+/// Lists `files` with room for line numbers as wide as any the pack can have, which is
+/// enough to measure the table before the files are laid out.
+pub(crate) fn estimate(files: &[PackedFile]) -> Vec<TocEntry> {
+    // Every format adds only a few lines per file around its contents
+    let widest = files.iter().map(|file| file.lines + 8).sum::<usize>() + 100;
+    files
+        .iter()
+        .map(|file| TocEntry::new(file, widest))
+        .collect()
+}
+
+/// Lists `files` with the line of the pack each one starts at, when they are laid out by a
+/// formatter of `formatters` after the header of `info`.
+///
+/// Returns an empty table for formats that do not write contents line for line.
+pub(crate) fn build(
+    formatters: &FormatterFactory,
+    files: &[PackedFile],
+    info: &PackInfo,
+) -> io::Result<Vec<TocEntry>> {
+    let mut formatter = formatters.formatter_for(files);
+    let Some(offset) = formatter.content_offset() else {
+        return Ok(Vec::new());
+    };
+
+    // Line numbers do not change how many lines the table takes, so measure the header with
+    // the estimate.
+    let mut section = Vec::new();
+    let mut measured = info.clone();
+    measured.toc = estimate(files);
+    formatter.write_header(&mut section, &measured)?;
+    let mut line = count_lines(&section) + 1;
+
+    let mut toc = Vec::with_capacity(files.len());
+    for file in files {
+        toc.push(TocEntry::new(file, line + offset));
+        section.clear();
+        formatter.write_file(&mut section, file)?;
+        line += count_lines(&section);
+    }
+    Ok(toc)
+}
+
+fn count_lines(section: &[u8]) -> usize {
+    section.iter().filter(|&&byte| byte == b'\n').count()
+}
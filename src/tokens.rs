@@ -0,0 +1,95 @@
+use log::warn;
+use std::{fmt, str::FromStr, sync::OnceLock};
+use tiktoken_rs::CoreBPE;
+
+/// Counts the tokens a piece of text costs in a model's context window.
+pub trait Tokenizer: Send + Sync {
+    fn count(&self, text: &str) -> usize;
+}
+
+/// The built-in tokenizers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TokenizerKind {
+    /// The BPE encoding used by GPT-4o and newer OpenAI models.
+    O200k,
+    /// The BPE encoding used by GPT-4 and GPT-3.5.
+    Cl100k,
+    /// A cheap approximation of one token per four characters.
+    #[default]
+    Estimate,
+}
+
+impl TokenizerKind {
+    /// Builds the tokenizer, falling back to the estimator if the BPE table cannot be loaded.
+    ///
+    /// Tables are loaded once per process and shared by every tokenizer built afterwards.
+    pub fn tokenizer(self) -> Box<dyn Tokenizer> {
+        static O200K: OnceLock<Option<CoreBPE>> = OnceLock::new();
+        static CL100K: OnceLock<Option<CoreBPE>> = OnceLock::new();
+
+        let (table, load): (_, fn() -> anyhow::Result<CoreBPE>) = match self {
+            TokenizerKind::O200k => (&O200K, tiktoken_rs::o200k_base),
+            TokenizerKind::Cl100k => (&CL100K, tiktoken_rs::cl100k_base),
+            TokenizerKind::Estimate => return Box::new(Estimate),
+        };
+
+        let bpe = table.get_or_init(|| {
+            load()
+                .map_err(|err| {
+                    warn!(
+                        "Failed to load the {} table, estimating tokens: {}",
+                        self, err
+                    )
+                })
+                .ok()
+        });
+        match bpe {
+            Some(bpe) => Box::new(Bpe(bpe)),
+            None => Box::new(Estimate),
+        }
+    }
+}
+
+impl FromStr for TokenizerKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "o200k" | "o200k_base" => Ok(TokenizerKind::O200k),
+            "cl100k" | "cl100k_base" => Ok(TokenizerKind::Cl100k),
+            "estimate" | "chars" => Ok(TokenizerKind::Estimate),
+            _ => Err(format!(
+                "unknown tokenizer '{}' (expected one of: o200k, cl100k, estimate)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for TokenizerKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TokenizerKind::O200k => "o200k",
+            TokenizerKind::Cl100k => "cl100k",
+            TokenizerKind::Estimate => "estimate",
+        })
+    }
+}
+
+/// A byte-pair encoding tokenizer backed by a bundled OpenAI table.
+pub struct Bpe(&'static CoreBPE);
+
+impl Tokenizer for Bpe {
+    fn count(&self, text: &str) -> usize {
+        self.0.encode_ordinary(text).len()
+    }
+}
+
+/// Estimates one token per four characters, rounding up.
+pub struct Estimate;
+
+impl Tokenizer for Estimate {
+    fn count(&self, text: &str) -> usize {
+        text.chars().count().div_ceil(4)
+    }
+}
@@ -0,0 +1,106 @@
+use crate::format::OmittedFile;
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, HashSet},
+    ffi::OsString,
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// Which files the directory tree at the top of a pack shows.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TreeMode {
+    /// No tree.
+    #[default]
+    Off,
+    /// The files in the pack.
+    Included,
+    /// Every file found by the walk, with those left out of the pack marked as such.
+    All,
+}
+
+impl FromStr for TreeMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "off" | "none" => Ok(TreeMode::Off),
+            "included" => Ok(TreeMode::Included),
+            "all" => Ok(TreeMode::All),
+            _ => Err(format!(
+                "unknown tree mode '{}' (expected one of: off, included, all)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for TreeMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TreeMode::Off => "off",
+            TreeMode::Included => "included",
+            TreeMode::All => "all",
+        })
+    }
+}
+
+/// A file shown in the directory tree.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TreeEntry {
+    /// Relative to the packed directory.
+    pub path: PathBuf,
+    /// Set for files found by the walk but not selected for the pack.
+    pub excluded: bool,
+}
+
+#[derive(Default)]
+struct Node {
+    children: BTreeMap<OsString, Node>,
+    note: Option<&'static str>,
+}
+
+/// Renders the entries like `tree`, marking excluded files and those omitted to fit the
+/// token budget.
+pub(crate) fn render(entries: &[TreeEntry], omitted: &[OmittedFile]) -> String {
+    let omitted: HashSet<&Path> = omitted.iter().map(|file| file.path.as_path()).collect();
+    let mut root = Node::default();
+    for entry in entries {
+        let mut node = &mut root;
+        for component in entry.path.components() {
+            node = node
+                .children
+                .entry(component.as_os_str().to_owned())
+                .or_default();
+        }
+        node.note = if entry.excluded {
+            Some("excluded")
+        } else if omitted.contains(entry.path.as_path()) {
+            Some("omitted")
+        } else {
+            None
+        };
+    }
+
+    let mut tree = String::from(".\n");
+    write_children(&mut tree, &root, "");
+    tree
+}
+
+fn write_children(tree: &mut String, node: &Node, indent: &str) {
+    let mut children = node.children.iter().peekable();
+    while let Some((name, child)) = children.next() {
+        let last = children.peek().is_none();
+        tree.push_str(indent);
+        tree.push_str(if last { "└── " } else { "├── " });
+        tree.push_str(&Path::new(name).display().to_string());
+        if let Some(note) = child.note {
+            tree.push_str(&format!(" ({})", note));
+        }
+        tree.push('\n');
+
+        let indent = format!("{}{}", indent, if last { "    " } else { "│   " });
+        write_children(tree, child, &indent);
+    }
+}
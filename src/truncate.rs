@@ -0,0 +1,313 @@
+use crate::file::LineNumbers;
+use std::{fmt, str::FromStr};
+
+/// The largest a file may be before it is cut down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileSizeLimit {
+    /// Size of the file on disk.
+    Bytes(usize),
+    Lines(usize),
+}
+
+impl FromStr for FileSizeLimit {
+    type Err = String;
+
+    /// Parses a number of bytes with an optional `KB`, `MB` or `GB` suffix (powers of
+    /// 1024), or a number of lines followed by `lines`, e.g. `500KB` or `2000lines`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lower = s.trim().to_ascii_lowercase();
+        let digits = lower.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+        let unit = lower[digits.len()..].to_string();
+        let n: usize = digits
+            .trim()
+            .parse()
+            .map_err(|_| format!("invalid file size '{}' (e.g. 500KB or 2000lines)", s))?;
+
+        let bytes = |scale: usize| Ok(FileSizeLimit::Bytes(n.saturating_mul(scale)));
+        match unit.as_str() {
+            "" | "b" => bytes(1),
+            "k" | "kb" => bytes(1 << 10),
+            "m" | "mb" => bytes(1 << 20),
+            "g" | "gb" => bytes(1 << 30),
+            "l" | "line" | "lines" => Ok(FileSizeLimit::Lines(n)),
+            _ => Err(format!(
+                "unknown unit '{}' in file size '{}' (expected B, KB, MB, GB or lines)",
+                unit, s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for FileSizeLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FileSizeLimit::Bytes(n) => write!(f, "{} bytes", n),
+            FileSizeLimit::Lines(n) => write!(f, "{} lines", n),
+        }
+    }
+}
+
+/// What is done with a file over the size limit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TruncateStrategy {
+    /// Leave the file out, listing it as skipped.
+    #[default]
+    Skip,
+    /// Keep the start of the file.
+    Head,
+    /// Keep the start and the end of the file, with a marker for the lines in between.
+    HeadTail,
+    /// Keep only the lines that look like declarations (functions, types, imports...).
+    Signatures,
+}
+
+impl FromStr for TruncateStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "skip" => Ok(TruncateStrategy::Skip),
+            "head" => Ok(TruncateStrategy::Head),
+            "head-tail" | "headtail" => Ok(TruncateStrategy::HeadTail),
+            "signatures" | "summarize" => Ok(TruncateStrategy::Signatures),
+            _ => Err(format!(
+                "unknown truncate strategy '{}' (expected one of: skip, head, head-tail, signatures)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for TruncateStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TruncateStrategy::Skip => "skip",
+            TruncateStrategy::Head => "head",
+            TruncateStrategy::HeadTail => "head-tail",
+            TruncateStrategy::Signatures => "signatures",
+        })
+    }
+}
+
+/// Words starting the lines kept by [`TruncateStrategy::Signatures`], in the languages
+/// packs usually hold.
+const SIGNATURE_KEYWORDS: &[&str] = &[
+    "fn",
+    "pub",
+    "async",
+    "struct",
+    "enum",
+    "union",
+    "trait",
+    "impl",
+    "mod",
+    "type",
+    "const",
+    "static",
+    "macro_rules!",
+    "class",
+    "def",
+    "function",
+    "export",
+    "interface",
+    "func",
+    "package",
+    "import",
+    "use",
+    "from",
+    "module",
+    "namespace",
+    "public",
+    "private",
+    "protected",
+    "abstract",
+    "#include",
+    "#define",
+    "create",
+    "alter",
+];
+
+/// Signature lines are cut to this many characters, so a minified file stays small.
+const MAX_SIGNATURE_LEN: usize = 200;
+
+/// Cuts `content` down to `limit` with `strategy`, numbering the kept lines with their
+/// line in the file if `line_numbers` is set, and tells whether anything was cut. Returns
+/// `None` if the file should be skipped.
+///
+/// Content within the limit is returned as is.
+pub(crate) fn truncate(
+    content: &str,
+    size_on_disk: u64,
+    limit: FileSizeLimit,
+    strategy: TruncateStrategy,
+    line_numbers: Option<&LineNumbers>,
+) -> Option<(String, bool)> {
+    let lines: Vec<&str> = content.split_inclusive('\n').collect();
+    let within = match limit {
+        FileSizeLimit::Bytes(max) => size_on_disk <= max as u64,
+        FileSizeLimit::Lines(max) => lines.len() <= max,
+    };
+    let number = |text: &str, first_line: usize| match line_numbers {
+        Some(line_numbers) => line_numbers.apply_from(text, first_line, lines.len()),
+        None => text.to_string(),
+    };
+    if within {
+        return Some((number(content, 1), false));
+    }
+
+    // Start and end of every byte range of the content to keep, in order
+    let kept = match strategy {
+        TruncateStrategy::Skip => return None,
+        TruncateStrategy::Head => vec![(0, head(content, &lines, limit))],
+        TruncateStrategy::HeadTail => {
+            let (head_limit, tail_limit) = match limit {
+                FileSizeLimit::Bytes(max) => (
+                    FileSizeLimit::Bytes(max - max / 2),
+                    FileSizeLimit::Bytes(max / 2),
+                ),
+                FileSizeLimit::Lines(max) => (
+                    FileSizeLimit::Lines(max - max / 2),
+                    FileSizeLimit::Lines(max / 2),
+                ),
+            };
+            let end = head(content, &lines, head_limit);
+            let start = tail(content, &lines, tail_limit).max(end);
+            vec![(0, end), (start, content.len())]
+        }
+        TruncateStrategy::Signatures => {
+            return Some((signatures(&lines, limit, &number), true));
+        }
+    };
+
+    let mut truncated = String::new();
+    let mut shown = 0;
+    for (start, end) in kept.into_iter().filter(|(start, end)| start < end) {
+        truncated.push_str(&elision(&content[shown..start]));
+        let first_line = content[..start].matches('\n').count() + 1;
+        truncated.push_str(&number(&content[start..end], first_line));
+        if !truncated.ends_with('\n') {
+            truncated.push('\n');
+        }
+        shown = end;
+    }
+    truncated.push_str(&elision(&content[shown..]));
+    Some((truncated, true))
+}
+
+/// Marks text left out of a truncated file, if there is any.
+fn elision(left_out: &str) -> String {
+    let (count, unit) = match left_out.split_inclusive('\n').count() {
+        0 => return String::new(),
+        1 if !left_out.ends_with('\n') => (left_out.len(), "byte"),
+        lines => (lines, "line"),
+    };
+    format!(
+        "[... {} {}{} truncated by codepack ...]\n",
+        count,
+        unit,
+        if count == 1 { "" } else { "s" }
+    )
+}
+
+/// Where the first lines of a file that fit in `limit` end, cutting into the first line if
+/// even it does not fit.
+fn head(content: &str, lines: &[&str], limit: FileSizeLimit) -> usize {
+    match limit {
+        FileSizeLimit::Bytes(max) => {
+            let mut end = 0;
+            for line in lines {
+                if end + line.len() > max {
+                    break;
+                }
+                end += line.len();
+            }
+            if end == 0 {
+                end = floor_char_boundary(content, max);
+            }
+            end
+        }
+        FileSizeLimit::Lines(max) => lines.iter().take(max).map(|line| line.len()).sum(),
+    }
+}
+
+/// Where the last lines of a file that fit in `limit` start, cutting into the last line if
+/// even it does not fit.
+fn tail(content: &str, lines: &[&str], limit: FileSizeLimit) -> usize {
+    let size = match limit {
+        FileSizeLimit::Bytes(max) => {
+            let mut size = 0;
+            for line in lines.iter().rev() {
+                if size + line.len() > max {
+                    break;
+                }
+                size += line.len();
+            }
+            if size == 0 {
+                let mut start = content.len().saturating_sub(max);
+                while !content.is_char_boundary(start) {
+                    start += 1;
+                }
+                return start;
+            }
+            size
+        }
+        FileSizeLimit::Lines(max) => lines.iter().rev().take(max).map(|line| line.len()).sum(),
+    };
+    content.len() - size
+}
+
+/// The declarations of a file, each cut to a readable length, for as many as fit in
+/// `limit`.
+fn signatures(
+    lines: &[&str],
+    limit: FileSizeLimit,
+    number: &dyn Fn(&str, usize) -> String,
+) -> String {
+    let mut kept = format!(
+        "[... only declarations are kept of this {}-line file, the rest is truncated by codepack ...]\n",
+        lines.len()
+    );
+    let header = kept.len();
+    let mut count = 0;
+    for (i, line) in lines.iter().enumerate() {
+        if !is_signature(line) {
+            continue;
+        }
+
+        let text = line.trim_end_matches(['\n', '\r']);
+        let cut = floor_char_boundary(text, MAX_SIGNATURE_LEN);
+        let mut line = number(&text[..cut], i + 1);
+        line.push_str(if cut < text.len() { " ...\n" } else { "\n" });
+
+        let full = match limit {
+            FileSizeLimit::Bytes(max) => kept.len() - header + line.len() > max,
+            FileSizeLimit::Lines(max) => count == max,
+        };
+        if full {
+            break;
+        }
+        kept.push_str(&line);
+        count += 1;
+    }
+    kept
+}
+
+fn is_signature(line: &str) -> bool {
+    let first_word = line
+        .trim_start()
+        .split(|c: char| c.is_whitespace() || c == '(' || c == '<')
+        .next()
+        .unwrap_or("");
+    SIGNATURE_KEYWORDS
+        .iter()
+        .any(|keyword| first_word.eq_ignore_ascii_case(keyword))
+}
+
+/// The largest index at most `index` that is on a character boundary of `s`.
+fn floor_char_boundary(s: &str, index: usize) -> usize {
+    let mut index = index.min(s.len());
+    while !s.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
@@ -0,0 +1,463 @@
+use crate::{
+    error::{Error, Result},
+    file::{Chunk, LineNumbers, PackedFile, TRUNCATED_MARKER},
+    format::{BOUNDARY_PREFIX, LINE_NUMBERS_NOTE, NO_NEWLINE_MARKER},
+};
+use log::{debug, warn};
+use serde::Deserialize;
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Component, Path, PathBuf},
+};
+
+/// Recreates the files of a pack on disk, below a destination directory.
+pub struct Unpacker {
+    destination: PathBuf,
+    skip_truncated: bool,
+}
+
+#[derive(Deserialize)]
+struct JsonFile {
+    path: PathBuf,
+    content: String,
+    chunk: Option<Chunk>,
+    #[serde(default)]
+    truncated: bool,
+    number_separator: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct JsonDocument {
+    files: Vec<JsonFile>,
+}
+
+impl Unpacker {
+    pub fn new<P: Into<PathBuf>>(destination: P) -> Self {
+        Self {
+            destination: destination.into(),
+            skip_truncated: false,
+        }
+    }
+
+    /// Leaves out files truncated when they were packed instead of failing on them, as
+    /// writing one would cut the file on disk short.
+    pub fn skip_truncated(mut self, skip_truncated: bool) -> Self {
+        self.skip_truncated = skip_truncated;
+        self
+    }
+
+    /// Reads the pack at `pack_path` and writes its files, returning the paths written.
+    pub fn unpack_file(&self, pack_path: &Path) -> Result<Vec<PathBuf>> {
+        let pack = fs::read_to_string(pack_path).map_err(Error::io(pack_path))?;
+        self.unpack(&pack)
+    }
+
+    /// Writes the files contained in `pack`, returning the paths written.
+    ///
+    /// Every path is validated before anything is written, so a pack that tries to escape the
+    /// destination, with `..`, an absolute path or through a symbolic link already in it,
+    /// leaves the destination untouched. Pieces of a file split across parts are
+    /// appended to it, so unpacking every part in order rebuilds the whole file.
+    ///
+    /// A pack holding a truncated file is refused unless such files are skipped.
+    pub fn unpack(&self, pack: &str) -> Result<Vec<PathBuf>> {
+        let mut files = Self::parse(pack)?;
+        if let Some(file) = files.iter().find(|file| file.truncated) {
+            if !self.skip_truncated {
+                return Err(Error::InvalidPack(format!(
+                    "{} was truncated when packed, so it cannot be unpacked",
+                    file.label()
+                )));
+            }
+        }
+        files.retain(|file| {
+            if file.truncated {
+                warn!("Skipping truncated file: {}", file.label());
+            }
+            !file.truncated
+        });
+        let targets = files
+            .iter()
+            .map(|file| {
+                let relative = sanitize_path(&file.path)?;
+                refuse_symlinks(&self.destination, &relative)?;
+                Ok(self.destination.join(relative))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        for (file, target) in files.iter().zip(&targets) {
+            let parent = target.parent().unwrap_or(&self.destination);
+            fs::create_dir_all(parent).map_err(Error::io(parent))?;
+
+            debug!("Unpacking file: {}", target.display());
+            let continued = file.chunk.is_some_and(|chunk| chunk.first_line > 1);
+            let mut options = OpenOptions::new();
+            options
+                .create(true)
+                .write(true)
+                .append(continued)
+                .truncate(!continued);
+            // A link could still appear after the paths were checked
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::OpenOptionsExt;
+                options.custom_flags(libc::O_NOFOLLOW);
+            }
+            options
+                .open(target)
+                .and_then(|mut f| f.write_all(file.content.as_bytes()))
+                .map_err(Error::io(target))?;
+        }
+
+        Ok(targets)
+    }
+
+    /// Parses a pack written in any of the built-in formats.
+    ///
+    /// Line numbers added to the contents with `--line-numbers` are removed, so the files
+    /// come back as they were packed. Truncated files are left as they are in the pack.
+    pub fn parse(pack: &str) -> Result<Vec<PackedFile>> {
+        Self::parse_numbered(pack)?
+            .into_iter()
+            .map(|mut file| {
+                let Some(separator) = file.number_separator.take().filter(|_| !file.truncated)
+                else {
+                    return Ok(file);
+                };
+                let line_numbers = LineNumbers {
+                    width: None,
+                    separator,
+                };
+                let content = line_numbers.strip(&file.content).map_err(|line| {
+                    Error::InvalidPack(format!(
+                        "line {} of {} has no line number",
+                        line,
+                        file.label()
+                    ))
+                })?;
+                Ok(PackedFile {
+                    chunk: file.chunk,
+                    truncated: file.truncated,
+                    ..PackedFile::new(file.path, content)
+                })
+            })
+            .collect()
+    }
+
+    fn parse_numbered(pack: &str) -> Result<Vec<PackedFile>> {
+        let trimmed = pack.trim_start();
+        // A pack without its preamble may still start with the line numbers note
+        let first_line = pack
+            .lines()
+            .find(|line| !line.is_empty() && !line.starts_with(LINE_NUMBERS_NOTE.0));
+        if trimmed.starts_with('{') {
+            parse_json(trimmed)
+        } else if trimmed.starts_with("<files>")
+            || (trimmed.starts_with("<!--") && pack.contains("\n<files>"))
+        {
+            parse_xml(pack)
+        } else if first_line.is_some_and(|line| line.starts_with('#') || line.contains("Markdown"))
+        {
+            parse_markdown(pack)
+        } else if let Some(boundary) = find_boundary(pack) {
+            parse_text(pack, boundary)
+        } else {
+            parse_legacy_text(pack)
+        }
+    }
+}
+
+/// Splits a file label into its path, whether it is truncated and, for a piece of a split
+/// file, its line range.
+fn parse_label(label: &str) -> (PathBuf, bool, Option<Chunk>) {
+    let (label, chunk) = label
+        .strip_suffix(')')
+        .and_then(|rest| {
+            let start = rest.rfind(" (lines ")?;
+            let chunk = rest[start + 2..].parse().ok()?;
+            Some((&rest[..start], Some(chunk)))
+        })
+        .unwrap_or((label, None));
+
+    match label.strip_suffix(TRUNCATED_MARKER) {
+        Some(path) => (PathBuf::from(path), true, chunk),
+        None => (PathBuf::from(label), false, chunk),
+    }
+}
+
+fn packed_file(label: &str, content: String, number_separator: &Option<String>) -> PackedFile {
+    let (path, truncated, chunk) = parse_label(label);
+    PackedFile {
+        chunk,
+        truncated,
+        number_separator: number_separator.clone(),
+        ..PackedFile::new(path, content)
+    }
+}
+
+/// Reads the separator after line numbers from the note written before the files of a
+/// numbered pack.
+fn number_separator(preamble: &str) -> Option<String> {
+    preamble.lines().find_map(|line| {
+        let note = line
+            .strip_prefix("<!-- ")
+            .and_then(|line| line.strip_suffix(" -->"))
+            .unwrap_or(line);
+        let separator = note
+            .strip_prefix(LINE_NUMBERS_NOTE.0)?
+            .strip_suffix(LINE_NUMBERS_NOTE.1)?;
+        Some(separator.to_string())
+    })
+}
+
+/// Rejects paths that could escape the destination and drops `.` components.
+fn sanitize_path(path: &Path) -> Result<PathBuf> {
+    let mut sanitized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => sanitized.push(part),
+            Component::CurDir => {}
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => {
+                return Err(Error::UnsafePath(path.to_owned()))
+            }
+        }
+    }
+
+    if sanitized.as_os_str().is_empty() {
+        return Err(Error::InvalidPack("empty path".to_string()));
+    }
+    Ok(sanitized)
+}
+
+/// Refuses a path below `destination` that goes through a symbolic link, which could point
+/// outside of it.
+fn refuse_symlinks(destination: &Path, relative: &Path) -> Result<()> {
+    let mut path = destination.to_path_buf();
+    for component in relative.components() {
+        path.push(component);
+        match fs::symlink_metadata(&path) {
+            Ok(metadata) if metadata.file_type().is_symlink() => {
+                return Err(Error::UnsafePath(relative.to_owned()))
+            }
+            Ok(_) => {}
+            // Nothing below a missing directory exists either
+            Err(err) if err.kind() == io::ErrorKind::NotFound => break,
+            Err(err) => return Err(Error::Io { path, source: err }),
+        }
+    }
+    Ok(())
+}
+
+fn parse_json(pack: &str) -> Result<Vec<PackedFile>> {
+    let files = match serde_json::from_str::<JsonDocument>(pack) {
+        Ok(document) => document.files,
+        // Not a single document, so read it as JSON Lines
+        Err(_) => pack
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str::<JsonFile>)
+            .collect::<Result<_, _>>()
+            .map_err(|err| Error::InvalidPack(format!("invalid JSON: {}", err)))?,
+    };
+
+    Ok(files
+        .into_iter()
+        .map(|file| PackedFile {
+            chunk: file.chunk,
+            truncated: file.truncated,
+            number_separator: file.number_separator,
+            ..PackedFile::new(file.path, file.content)
+        })
+        .collect())
+}
+
+fn parse_xml(pack: &str) -> Result<Vec<PackedFile>> {
+    const CDATA_START: &str = "<![CDATA[";
+    const CDATA_END: &str = "]]>";
+    let unterminated_file = || Error::InvalidPack("unterminated <file> element".to_string());
+
+    let mut files = Vec::new();
+    let number_separator = number_separator(pack.split("\n<files>").next().unwrap_or(""));
+    let mut rest = pack;
+
+    while let Some(start) = rest.find("<file path=\"") {
+        rest = &rest[start + "<file path=\"".len()..];
+        let end = rest.find('"').ok_or_else(unterminated_file)?;
+        let path = unescape_xml_attribute(&rest[..end]);
+        let close = rest.find('>').ok_or_else(unterminated_file)?;
+        let attributes = &rest[end..close];
+        let chunk = attributes
+            .split_once(" lines=\"")
+            .and_then(|(_, range)| range.split('"').next()?.parse().ok());
+        let truncated = attributes.contains(" truncated=\"true\"");
+        rest = &rest[close + 1..];
+
+        // Contents may be split over several CDATA sections to escape `]]>`.
+        let mut content = String::new();
+        while let Some(section) = rest.strip_prefix(CDATA_START) {
+            let end = section.find(CDATA_END).ok_or_else(|| {
+                Error::InvalidPack(format!("unterminated CDATA section in {}", path))
+            })?;
+            content.push_str(&section[..end]);
+            rest = &section[end + CDATA_END.len()..];
+        }
+
+        rest = rest
+            .strip_prefix("</file>")
+            .ok_or_else(|| Error::InvalidPack(format!("malformed <file> element for {}", path)))?;
+        files.push(PackedFile {
+            chunk,
+            truncated,
+            number_separator: number_separator.clone(),
+            ..PackedFile::new(PathBuf::from(path), content)
+        });
+    }
+
+    Ok(files)
+}
+
+fn parse_markdown(pack: &str) -> Result<Vec<PackedFile>> {
+    let mut files = Vec::new();
+    let mut preamble = String::new();
+    let mut number_separator = None;
+    let mut lines = pack.split_inclusive('\n').peekable();
+    let missing_block = |label| Error::InvalidPack(format!("missing code block for {}", label));
+
+    while let Some(line) = lines.next() {
+        let Some(label) = line.trim_end().strip_prefix("## ") else {
+            if files.is_empty() {
+                preamble.push_str(line);
+            }
+            continue;
+        };
+        if files.is_empty() {
+            number_separator = self::number_separator(&preamble);
+        }
+
+        // Skip the blank line between the heading and the opening fence
+        let fence_line = lines
+            .by_ref()
+            .find(|l| !l.trim().is_empty())
+            .ok_or_else(|| missing_block(label))?;
+        let fence: String = fence_line.chars().take_while(|c| *c == '`').collect();
+        if fence.len() < 3 {
+            return Err(missing_block(label));
+        }
+
+        let mut content = String::new();
+        for line in lines.by_ref() {
+            if line.trim_end() == fence {
+                break;
+            }
+            content.push_str(line);
+        }
+        // The newline before the fence was only added for it
+        if lines
+            .next_if(|line| line.trim_end() == NO_NEWLINE_MARKER)
+            .is_some()
+            && content.ends_with('\n')
+        {
+            content.pop();
+        }
+        files.push(packed_file(label, content, &number_separator));
+    }
+
+    Ok(files)
+}
+
+/// Finds the boundary of a plain text pack from its first delimiter line.
+pub(crate) fn find_boundary(pack: &str) -> Option<&str> {
+    pack.lines().find_map(|line| {
+        let boundary = line.strip_prefix("--")?;
+        let hex = boundary.strip_prefix(BOUNDARY_PREFIX)?;
+        let len = hex.chars().take_while(|c| c.is_ascii_hexdigit()).count();
+        let rest = &hex[len..];
+        (len == 32 && (rest.starts_with(' ') || rest == "--"))
+            .then(|| &boundary[..BOUNDARY_PREFIX.len() + len])
+    })
+}
+
+fn parse_text(pack: &str, boundary: &str) -> Result<Vec<PackedFile>> {
+    let delimiter = format!("--{}", boundary);
+    let separator = format!("\n{}", delimiter);
+
+    let mut files = Vec::new();
+    // The preamble mentions the boundary too, so only delimiters at the start of a line count.
+    let (preamble, mut rest) = match pack.strip_prefix(&delimiter) {
+        Some(rest) => ("", rest),
+        None => match pack.find(&separator) {
+            Some(start) => (&pack[..start], &pack[start + separator.len()..]),
+            None => return Ok(files),
+        },
+    };
+    let number_separator = number_separator(preamble);
+
+    // `rest` always starts right after a delimiter: either " path\n..." or "--" at the end.
+    while let Some(section) = rest.strip_prefix(' ') {
+        let (label, body) = section
+            .split_once('\n')
+            .ok_or_else(|| Error::InvalidPack("unterminated file delimiter".to_string()))?;
+
+        let content = match body.find(&separator) {
+            Some(end) => {
+                rest = &body[end + separator.len()..];
+                &body[..end]
+            }
+            // A pack without its closing delimiter, e.g. one truncated by an LLM
+            None => {
+                rest = "";
+                body.strip_suffix('\n').unwrap_or(body)
+            }
+        };
+        files.push(packed_file(label, content.to_string(), &number_separator));
+    }
+
+    Ok(files)
+}
+
+/// Parses the `--- path ---` layout written before packs carried a boundary.
+fn parse_legacy_text(pack: &str) -> Result<Vec<PackedFile>> {
+    // Each file is written as "\n--- path ---\n" followed by its content and a newline.
+    let mut headers = Vec::new();
+    let mut offset = 0;
+    let mut previous_blank = true;
+
+    for line in pack.split_inclusive('\n') {
+        let trimmed = line.trim_end_matches(['\n', '\r']);
+        if previous_blank {
+            if let Some(path) = trimmed
+                .strip_prefix("--- ")
+                .and_then(|rest| rest.strip_suffix(" ---"))
+            {
+                headers.push((offset, offset + line.len(), path));
+            }
+        }
+        previous_blank = trimmed.is_empty();
+        offset += line.len();
+    }
+
+    let mut files = Vec::with_capacity(headers.len());
+    for (i, (_, body_start, path)) in headers.iter().enumerate() {
+        let content = match headers.get(i + 1) {
+            Some((next_start, _, _)) => {
+                let body = &pack[*body_start..*next_start];
+                body.strip_suffix('\n').unwrap_or(body)
+            }
+            None => &pack[*body_start..],
+        };
+        let content = content.strip_suffix('\n').unwrap_or(content);
+        files.push(PackedFile::new(PathBuf::from(path), content.to_string()));
+    }
+
+    Ok(files)
+}
+
+fn unescape_xml_attribute(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
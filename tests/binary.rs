@@ -0,0 +1,66 @@
+use codepack::DirectoryProcessor;
+use std::fs;
+use tempfile::TempDir;
+
+fn setup() -> TempDir {
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(temp_dir.path().join("main.rs"), "fn main() {}\n").unwrap();
+    fs::write(temp_dir.path().join("module.wasm"), b"\0asm\x01\0\0\0").unwrap();
+    fs::write(temp_dir.path().join("app"), b"\x7fELF\x02\x01\x01").unwrap();
+    fs::write(temp_dir.path().join("data.sqlite"), b"SQLite format 3\0").unwrap();
+    fs::write(temp_dir.path().join("blob"), b"text\0more text").unwrap();
+    fs::write(temp_dir.path().join("noise"), b"\x01\x02\x03\x04\xff\x80ab").unwrap();
+    fs::write(temp_dir.path().join("logo.svg"), "<svg/>").unwrap();
+    temp_dir
+}
+
+fn paths(processor: &DirectoryProcessor, temp_dir: &TempDir) -> Vec<String> {
+    processor
+        .collect(temp_dir.path())
+        .unwrap()
+        .iter()
+        .map(|file| file.path.display().to_string())
+        .collect()
+}
+
+#[test]
+fn test_binary_files_are_skipped() {
+    let temp_dir = setup();
+    let processor = DirectoryProcessor::builder().build().unwrap();
+    assert_eq!(paths(&processor, &temp_dir), ["main.rs"]);
+}
+
+#[test]
+fn test_force_text_overrides_sniffing() {
+    let temp_dir = setup();
+    let processor = DirectoryProcessor::builder()
+        .force_text(["*.svg", "blob"])
+        .build()
+        .unwrap();
+    assert_eq!(
+        paths(&processor, &temp_dir),
+        ["blob", "logo.svg", "main.rs"]
+    );
+
+    let files = processor.collect(temp_dir.path()).unwrap();
+    assert_eq!(files[0].content, "text\0more text");
+}
+
+#[test]
+fn test_force_text_globs_match_relative_paths() {
+    let temp_dir = TempDir::new().unwrap();
+    fs::create_dir_all(temp_dir.path().join("sub/deep")).unwrap();
+    fs::write(temp_dir.path().join("sub/blob"), b"text\0more text").unwrap();
+    fs::write(temp_dir.path().join("sub/deep/blob"), b"text\0more text").unwrap();
+
+    let processor = DirectoryProcessor::builder()
+        .force_text(["sub/*"])
+        .build()
+        .unwrap();
+    let paths: Vec<String> = paths(&processor, &temp_dir)
+        .into_iter()
+        .map(|path| path.replace('\\', "/"))
+        .collect();
+    // `*` stays within a directory
+    assert_eq!(paths, ["sub/blob"]);
+}
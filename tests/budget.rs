@@ -0,0 +1,82 @@
+use codepack::{BudgetStrategy, DirectoryProcessor, Format, TokenBudget, TokenizerKind};
+use std::{fs, path::Path};
+use tempfile::TempDir;
+
+fn setup() -> TempDir {
+    let temp_dir = TempDir::new().unwrap();
+    fs::create_dir_all(temp_dir.path().join("src/deep")).unwrap();
+    fs::write(temp_dir.path().join("top.rs"), "fn top() {}\n".repeat(170)).unwrap();
+    fs::write(
+        temp_dir.path().join("src/mid.rs"),
+        "fn mid() {}\n".repeat(85),
+    )
+    .unwrap();
+    fs::write(temp_dir.path().join("src/deep/tiny.rs"), "fn tiny() {}\n").unwrap();
+    temp_dir
+}
+
+fn pack(dir: &Path, format: Format, budget: TokenBudget) -> (codepack::PackSummary, String) {
+    let output = dir.join("pack.out");
+    let summary = DirectoryProcessor::builder()
+        .extensions(["rs"])
+        .output(output.to_str().unwrap())
+        .force(true)
+        .format(format)
+        .tokenizer(TokenizerKind::Estimate)
+        .token_budget(budget)
+        .build()
+        .unwrap()
+        .run(dir)
+        .expect("Failed to run processor");
+    (summary, fs::read_to_string(output).unwrap())
+}
+
+fn packed(summary: &codepack::PackSummary) -> Vec<String> {
+    let mut paths: Vec<String> = summary
+        .files
+        .iter()
+        .map(|f| f.path.display().to_string())
+        .collect();
+    paths.sort();
+    paths
+}
+
+#[test]
+fn test_budget_keeps_smallest_files_and_lists_omitted() {
+    let temp_dir = setup();
+    let (summary, output) = pack(temp_dir.path(), Format::Text, TokenBudget::new(700));
+
+    assert!(summary.tokens <= 700, "{} tokens", summary.tokens);
+    assert_eq!(packed(&summary), ["src/deep/tiny.rs", "src/mid.rs"]);
+    assert_eq!(summary.omitted.len(), 1);
+    assert_eq!(summary.omitted[0].path, Path::new("top.rs"));
+    assert!(output.contains("omitted to fit the token budget of 700 tokens:\n- top.rs ("));
+}
+
+#[test]
+fn test_budget_strategy_and_priority() {
+    let temp_dir = setup();
+
+    let shallowest = TokenBudget::new(700).with_strategy(BudgetStrategy::Shallowest);
+    let (summary, _) = pack(temp_dir.path(), Format::Text, shallowest);
+    assert_eq!(packed(&summary), ["src/deep/tiny.rs", "top.rs"]);
+
+    let priority = TokenBudget::new(700)
+        .with_priority(["src/*.rs"])
+        .unwrap()
+        .with_strategy(BudgetStrategy::Shallowest);
+    let (summary, _) = pack(temp_dir.path(), Format::Text, priority);
+    assert_eq!(packed(&summary), ["src/deep/tiny.rs", "src/mid.rs"]);
+
+    assert!(TokenBudget::new(1).with_priority(["src/[oops"]).is_err());
+}
+
+#[test]
+fn test_budget_trailer_in_json() {
+    let temp_dir = setup();
+    let (summary, output) = pack(temp_dir.path(), Format::Json, TokenBudget::new(700));
+    assert!(summary.tokens <= 700, "{} tokens", summary.tokens);
+
+    let document: serde_json::Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(document["omitted"][0]["path"], "top.rs");
+}
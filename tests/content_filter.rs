@@ -0,0 +1,82 @@
+use codepack::{DirectoryProcessor, FilterExpr};
+use std::fs;
+use tempfile::TempDir;
+
+/// Lines of filler making a file larger than what is matched whole.
+fn filler() -> String {
+    "let value = compute(value);\n".repeat(50_000)
+}
+
+fn processor(filter: &str, output: &std::path::Path) -> DirectoryProcessor {
+    DirectoryProcessor::builder()
+        .output(output.display().to_string())
+        .filters([filter.parse::<FilterExpr>().unwrap()])
+        .suppress_prompt(true)
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn test_large_files_are_matched_by_streaming() {
+    let temp_dir = TempDir::new().unwrap();
+    let dir = temp_dir.path().join("project");
+    fs::create_dir(&dir).unwrap();
+    let needle_at_end = format!("{}fn needle() {{}}\n", filler());
+    fs::write(dir.join("end.rs"), &needle_at_end).unwrap();
+    fs::write(dir.join("none.rs"), filler()).unwrap();
+    // Latin-1 is recognised from the start of the file
+    let mut latin1 = b"// caf\xe9 au lait\n".to_vec();
+    latin1.extend_from_slice(filler().as_bytes());
+    fs::write(dir.join("latin1.rs"), latin1).unwrap();
+
+    let paths = |filter: &str| -> Vec<String> {
+        processor(filter, &temp_dir.path().join("pack.txt"))
+            .collect(&dir)
+            .unwrap()
+            .iter()
+            .map(|file| file.path.display().to_string())
+            .collect()
+    };
+    assert_eq!(paths("content.contains=needle"), ["end.rs"]);
+    assert_eq!(paths("content.regex=^fn needle"), ["end.rs"]);
+    assert_eq!(paths("content.icontains=CAFÉ"), ["latin1.rs"]);
+    assert_eq!(
+        paths("content.contains=compute AND NOT content.contains=needle"),
+        ["latin1.rs", "none.rs"]
+    );
+    assert_eq!(paths("lines>50000"), ["end.rs", "latin1.rs"]);
+    assert_eq!(paths("lines<=50000"), ["none.rs"]);
+
+    // A file matched while streaming is still packed whole
+    let files = processor("content.contains=needle", &temp_dir.path().join("pack.txt"))
+        .collect(&dir)
+        .unwrap();
+    assert_eq!(files[0].content, needle_at_end);
+}
+
+#[test]
+fn test_content_filters_count_only_matched_files() {
+    let temp_dir = TempDir::new().unwrap();
+    let dir = temp_dir.path().join("project");
+    fs::create_dir(&dir).unwrap();
+    fs::write(dir.join("todo.rs"), "// TODO: tests\n").unwrap();
+    fs::write(dir.join("done.rs"), "fn done() {}\n").unwrap();
+    fs::write(dir.join("big.rs"), filler()).unwrap();
+    fs::write(dir.join("image.png"), b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR").unwrap();
+
+    let output = temp_dir.path().join("pack.txt");
+    let summary = processor("content.contains=TODO OR file.name=done", &output)
+        .run(&dir)
+        .unwrap();
+    let packed: Vec<_> = summary
+        .files
+        .iter()
+        .map(|file| file.path.display().to_string())
+        .collect();
+    assert_eq!(packed, ["done.rs", "todo.rs"]);
+    assert!(summary.skipped.is_empty(), "{:?}", summary.skipped);
+
+    let pack = fs::read_to_string(&output).unwrap();
+    assert!(pack.contains("// TODO: tests"));
+    assert!(!pack.contains("compute(value)"));
+}
@@ -0,0 +1,106 @@
+use codepack::{DirectoryProcessor, Error};
+use std::fs;
+use tempfile::TempDir;
+
+fn utf16le(text: &str) -> Vec<u8> {
+    let mut bytes = vec![0xff, 0xfe];
+    bytes.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+    bytes
+}
+
+#[test]
+fn test_encodings_are_detected() {
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(temp_dir.path().join("utf16.txt"), utf16le("hello wörld\n")).unwrap();
+    fs::write(
+        temp_dir.path().join("windows1252.txt"),
+        b"caf\xe9 na\xefve r\xe9sum\xe9 \x93quoted\x94\n",
+    )
+    .unwrap();
+    fs::write(temp_dir.path().join("utf8.txt"), "\u{feff}keeps its BOM\n").unwrap();
+
+    let files = DirectoryProcessor::builder()
+        .build()
+        .unwrap()
+        .collect(temp_dir.path())
+        .unwrap();
+    let contents: Vec<&str> = files.iter().map(|file| file.content.as_str()).collect();
+    assert_eq!(
+        contents,
+        [
+            "hello wörld\n",
+            "\u{feff}keeps its BOM\n",
+            "café naïve résumé “quoted”\n"
+        ]
+    );
+}
+
+#[test]
+fn test_encoding_override() {
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(temp_dir.path().join("legacy.txt"), b"\xe0 la carte").unwrap();
+
+    let files = DirectoryProcessor::builder()
+        .encoding("legacy.txt", "latin1")
+        .build()
+        .unwrap()
+        .collect(temp_dir.path())
+        .unwrap();
+    assert_eq!(files[0].content, "à la carte");
+
+    let err = DirectoryProcessor::builder()
+        .encoding("*.txt", "shift_jis")
+        .strict(true)
+        .build()
+        .unwrap()
+        .collect(temp_dir.path())
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        Error::InvalidEncoding {
+            encoding: "Shift_JIS",
+            ..
+        }
+    ));
+
+    let err = DirectoryProcessor::builder()
+        .encoding("*.txt", "klingon")
+        .build()
+        .unwrap_err();
+    assert!(matches!(err, Error::InvalidOptions(_)));
+}
+
+#[test]
+fn test_encoding_globs_match_relative_paths() {
+    let temp_dir = TempDir::new().unwrap();
+    let project = temp_dir.path().join("proj");
+    fs::create_dir_all(project.join("legacy/deep")).unwrap();
+    for path in ["legacy/a.txt", "legacy/deep/b.txt", "c.txt"] {
+        fs::write(project.join(path), "abcd").unwrap();
+    }
+
+    let files = DirectoryProcessor::builder()
+        .encoding("legacy/*", "utf-16le")
+        .build()
+        .unwrap()
+        .collect(&project)
+        .unwrap();
+    let contents: Vec<(String, &str)> = files
+        .iter()
+        .map(|file| {
+            (
+                file.path.display().to_string().replace('\\', "/"),
+                file.content.as_str(),
+            )
+        })
+        .collect();
+    // `*` stays within a directory
+    assert_eq!(
+        contents,
+        [
+            ("c.txt".to_string(), "abcd"),
+            ("legacy/a.txt".to_string(), "\u{6261}\u{6463}"),
+            ("legacy/deep/b.txt".to_string(), "abcd"),
+        ]
+    );
+}
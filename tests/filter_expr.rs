@@ -0,0 +1,84 @@
+use codepack::{DirectoryProcessor, Error, Filter, FilterExpr};
+use std::fs;
+use tempfile::TempDir;
+
+fn paths(filters: &[&str]) -> Vec<String> {
+    let temp_dir = TempDir::new().unwrap();
+    fs::create_dir_all(temp_dir.path().join("src")).unwrap();
+    fs::write(temp_dir.path().join("src/lib.rs"), "pub fn lib() {}\n").unwrap();
+    fs::write(temp_dir.path().join("src/test.rs"), "// TODO: more tests\n").unwrap();
+    fs::write(temp_dir.path().join("notes.md"), "TODO: write docs\n").unwrap();
+    fs::write(temp_dir.path().join("README.md"), "# Project\n").unwrap();
+
+    let filters = filters
+        .iter()
+        .map(|filter| filter.parse::<FilterExpr>())
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    DirectoryProcessor::builder()
+        .filters(filters)
+        .build()
+        .unwrap()
+        .collect(temp_dir.path())
+        .unwrap()
+        .iter()
+        .map(|file| file.path.display().to_string().replace('\\', "/"))
+        .collect()
+}
+
+#[test]
+fn test_filter_expressions_select_files() {
+    assert_eq!(
+        paths(&["(path.contains=src AND NOT file.name=test) OR content.contains=TODO"]),
+        ["notes.md", "src/lib.rs", "src/test.rs"]
+    );
+    assert_eq!(
+        paths(&["content.contains=TODO and not file.name=notes"]),
+        ["src/test.rs"]
+    );
+    // Separate filters are still alternatives, and plain ones still work on their own
+    assert_eq!(
+        paths(&["file.name=README", "file.name=lib"]),
+        ["README.md", "src/lib.rs"]
+    );
+    assert_eq!(paths(&["content.contains=pub fn"]), ["src/lib.rs"]);
+    assert_eq!(
+        paths(&["content.contains=\"TODO: write\" OR file.name=lib"]),
+        ["notes.md", "src/lib.rs"]
+    );
+}
+
+#[test]
+fn test_filter_expression_parsing() {
+    let expr: FilterExpr = "NOT file.name=a OR path.contains=b AND content.contains=c"
+        .parse()
+        .unwrap();
+    assert_eq!(
+        expr,
+        FilterExpr::Or(vec![
+            FilterExpr::Not(Box::new(Filter::FileName("a".to_string()).into())),
+            FilterExpr::And(vec![
+                Filter::PathContains("b".to_string()).into(),
+                Filter::ContentContains("c".to_string()).into(),
+            ]),
+        ])
+    );
+    assert_eq!(
+        expr.to_string(),
+        "NOT file.name=a OR (path.contains=b AND content.contains=c)"
+    );
+
+    for (expression, expected_column) in [
+        ("file.name=a AND (path.contains=b", 17),
+        ("file.name=a OR", 15),
+        ("file.name=a OR owner=3", 16),
+        ("file.name=a OR content.contains='open", 33),
+    ] {
+        match expression.parse::<FilterExpr>() {
+            Err(Error::FilterSyntax { column, .. }) => {
+                assert_eq!(column, expected_column, "{}", expression)
+            }
+            other => panic!("{}: {:?}", expression, other),
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use codepack::{DirectoryProcessor, Error, Field, Filter, FilterExpr, PatternKind};
+use std::fs;
+use tempfile::TempDir;
+
+fn paths(filter: &str) -> Vec<String> {
+    let temp_dir = TempDir::new().unwrap();
+    fs::create_dir_all(temp_dir.path().join("src/api/users")).unwrap();
+    fs::write(
+        temp_dir.path().join("src/api/users/login_handler.rs"),
+        "#[tokio::test]\nasync fn login() {}\n",
+    )
+    .unwrap();
+    fs::write(
+        temp_dir.path().join("src/api/Health_Handler.rs"),
+        "pub fn health() {}\n",
+    )
+    .unwrap();
+    fs::write(
+        temp_dir.path().join("src/main_handler.rs"),
+        "#[test]\nfn main() {}\n",
+    )
+    .unwrap();
+    fs::write(temp_dir.path().join("README.md"), "# Handlers\n").unwrap();
+
+    DirectoryProcessor::builder()
+        .filters([filter.parse::<FilterExpr>().unwrap()])
+        .build()
+        .unwrap()
+        .collect(temp_dir.path())
+        .unwrap()
+        .iter()
+        .map(|file| file.path.display().to_string().replace('\\', "/"))
+        .collect()
+}
+
+#[test]
+fn test_regex_and_glob_filters_select_files() {
+    assert_eq!(
+        paths(r"content.regex=#\[tokio::test\]"),
+        ["src/api/users/login_handler.rs"]
+    );
+    assert_eq!(
+        paths("path.glob=src/api/**/*_handler.rs"),
+        ["src/api/users/login_handler.rs"]
+    );
+    assert_eq!(
+        paths("path.iglob=src/api/**/*_handler.rs"),
+        [
+            "src/api/Health_Handler.rs",
+            "src/api/users/login_handler.rs"
+        ]
+    );
+    assert_eq!(
+        paths("file.name~=^[a-z]+_handler\\.rs$"),
+        ["src/api/users/login_handler.rs", "src/main_handler.rs"]
+    );
+    assert_eq!(paths("file.glob=*.md"), ["README.md"]);
+    assert_eq!(
+        paths("content.icontains=HANDLERS OR file.iname=health"),
+        ["README.md", "src/api/Health_Handler.rs"]
+    );
+    assert_eq!(
+        paths("content.iregex='^(async )?FN' AND NOT path.regex=^src/api/"),
+        ["src/main_handler.rs"]
+    );
+}
+
+#[test]
+fn test_pattern_filter_parsing() {
+    let filter: Filter = "file.iname~=^main\\.rs$".parse().unwrap();
+    match &filter {
+        Filter::Match { field, pattern } => {
+            assert_eq!(*field, Field::FileName);
+            assert_eq!(pattern.kind(), PatternKind::Regex);
+            assert_eq!(pattern.as_str(), "^main\\.rs$");
+            assert!(pattern.ignores_case());
+        }
+        other => panic!("expected a pattern filter, got {:?}", other),
+    }
+    assert_eq!(filter.to_string(), "file.iname~=^main\\.rs$");
+    // Case-sensitive substrings are still the plain filters
+    assert_eq!(
+        "path.contains=src".parse::<Filter>().unwrap(),
+        Filter::PathContains("src".to_string())
+    );
+
+    assert!(matches!(
+        "content.regex=fn (".parse::<Filter>(),
+        Err(Error::InvalidRegex { .. })
+    ));
+    assert!(matches!(
+        "file.name=a OR content.regex='fn ('".parse::<FilterExpr>(),
+        Err(Error::InvalidRegex { .. })
+    ));
+    assert!(matches!(
+        "path.glob=src/[".parse::<Filter>(),
+        Err(Error::InvalidGlob { .. })
+    ));
+}
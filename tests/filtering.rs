@@ -3,70 +3,51 @@ use std::path::Path;
 
 #[test]
 fn test_should_process_file_empty_path() {
-    let processor = DirectoryProcessor::new(
-        vec!["rs".to_string()],
-        vec!["excluded.rs".to_string()],
-        false,
-        String::new(),
-        false,
-        vec![],
-    );
+    let processor = DirectoryProcessor::builder()
+        .extensions(["rs"])
+        .excluded_files(["excluded.rs"])
+        .build()
+        .unwrap();
     let path = Path::new("");
     assert!(!processor.should_process_file(path));
 }
 
 #[test]
 fn test_should_process_file_excluded_by_name() {
-    let processor = DirectoryProcessor::new(
-        vec![],
-        vec!["test.lock".to_string()],
-        false,
-        String::new(),
-        false,
-        vec![],
-    );
+    let processor = DirectoryProcessor::builder()
+        .excluded_files(["test.lock"])
+        .build()
+        .unwrap();
     let path = Path::new("test.lock");
     assert!(!processor.should_process_file(path));
 }
 
 #[test]
 fn test_should_process_file_excluded_by_extension() {
-    let processor = DirectoryProcessor::new(
-        vec!["rs".to_string()],
-        vec![],
-        false,
-        String::new(),
-        false,
-        vec![],
-    );
+    let processor = DirectoryProcessor::builder()
+        .extensions(["rs"])
+        .build()
+        .unwrap();
     let path = Path::new("main.py");
     assert!(!processor.should_process_file(path));
 }
 
 #[test]
 fn test_should_process_file_included_by_extension() {
-    let processor = DirectoryProcessor::new(
-        vec!["rs".to_string()],
-        vec![],
-        false,
-        String::new(),
-        false,
-        vec![],
-    );
+    let processor = DirectoryProcessor::builder()
+        .extensions(["rs"])
+        .build()
+        .unwrap();
     let path = Path::new("main.rs");
     assert!(processor.should_process_file(path));
 }
 
 #[test]
 fn test_should_process_file_filter_file_name() {
-    let processor = DirectoryProcessor::new(
-        vec![],
-        vec![],
-        false,
-        String::new(),
-        false,
-        vec![Filter::FileName("main.rs".to_string())],
-    );
+    let processor = DirectoryProcessor::builder()
+        .filters([Filter::FileName("main.rs".to_string())])
+        .build()
+        .unwrap();
     let path = Path::new("main.rs");
     assert!(processor.should_process_file(path));
 
@@ -76,14 +57,10 @@ fn test_should_process_file_filter_file_name() {
 
 #[test]
 fn test_should_process_file_filter_path_contains() {
-    let processor = DirectoryProcessor::new(
-        vec![],
-        vec![],
-        false,
-        String::new(),
-        false,
-        vec![Filter::PathContains("src".to_string())],
-    );
+    let processor = DirectoryProcessor::builder()
+        .filters([Filter::PathContains("src".to_string())])
+        .build()
+        .unwrap();
     let path = Path::new("src/main.rs");
     assert!(processor.should_process_file(path));
 
@@ -93,17 +70,14 @@ fn test_should_process_file_filter_path_contains() {
 
 #[test]
 fn test_should_process_file_combined_filters() {
-    let processor = DirectoryProcessor::new(
-        vec!["rs".to_string()],
-        vec![],
-        false,
-        String::new(),
-        false,
-        vec![
+    let processor = DirectoryProcessor::builder()
+        .extensions(["rs"])
+        .filters([
             Filter::FileName("main".to_string()),
             Filter::PathContains("src".to_string()),
-        ],
-    );
+        ])
+        .build()
+        .unwrap();
     let path = Path::new("src/main.rs");
     assert!(processor.should_process_file(path));
 
@@ -116,14 +90,12 @@ fn test_should_process_file_combined_filters() {
 
 #[test]
 fn test_should_process_file_exclusion_and_filters() {
-    let processor = DirectoryProcessor::new(
-        vec!["rs".to_string()],
-        vec!["excluded.rs".to_string()],
-        false,
-        String::new(),
-        false,
-        vec![Filter::PathContains("src".to_string())],
-    );
+    let processor = DirectoryProcessor::builder()
+        .extensions(["rs"])
+        .excluded_files(["excluded.rs"])
+        .filters([Filter::PathContains("src".to_string())])
+        .build()
+        .unwrap();
     let excluded_path = Path::new("src/excluded.rs");
     assert!(!processor.should_process_file(excluded_path));
 
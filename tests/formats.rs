@@ -0,0 +1,179 @@
+use codepack::{
+    language_for, DirectoryProcessor, Format, OutputFormat, PackInfo, PackedFile, SplitLimit,
+};
+use std::{
+    fs,
+    io::{self, Write},
+    path::Path,
+};
+use tempfile::TempDir;
+
+fn pack(dir: &Path, format: Format) -> String {
+    let output = dir.join(format!("pack.{}", format.extension()));
+    DirectoryProcessor::builder()
+        .extensions(["rs"])
+        .output(output.to_str().unwrap())
+        .force(true)
+        .format(format)
+        .build()
+        .unwrap()
+        .run(dir)
+        .expect("Failed to run processor");
+    fs::read_to_string(output).expect("Failed to read output")
+}
+
+#[test]
+fn test_markdown_format_fences_with_language() {
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(temp_dir.path().join("main.rs"), "/// ```\nfn main() {}").unwrap();
+
+    let output = pack(temp_dir.path(), Format::Markdown);
+    assert!(output.starts_with("This is a Markdown file"));
+    // The fence grows past the backticks inside the file.
+    assert!(output.contains("````rust\n/// ```\nfn main() {}\n````\n"));
+}
+
+#[test]
+fn test_xml_format_escapes_cdata_terminator() {
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(temp_dir.path().join("a&b.rs"), "let x = a[b[0]]>c;").unwrap();
+
+    let output = pack(temp_dir.path(), Format::Xml);
+    assert!(output.contains("a&amp;b.rs\"><![CDATA[let x = a[b[0]]]]><![CDATA[>c;]]></file>"));
+    assert!(output.trim_end().ends_with("</files>"));
+}
+
+#[test]
+fn test_format_from_str_and_language() {
+    assert_eq!("md".parse::<Format>(), Ok(Format::Markdown));
+    assert_eq!("XML".parse::<Format>(), Ok(Format::Xml));
+    assert!("yaml".parse::<Format>().is_err());
+
+    assert_eq!(language_for(Path::new("src/lib.rs")), Some("rust"));
+    assert_eq!(language_for(Path::new("Dockerfile")), Some("dockerfile"));
+    assert_eq!(language_for(Path::new("notes.unknown")), None);
+}
+
+#[test]
+fn test_json_format_reports_metadata() {
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(temp_dir.path().join("a.rs"), "--- b.rs ---\nfn a() {}\n").unwrap();
+    fs::write(temp_dir.path().join("b.rs"), "fn b() {}").unwrap();
+
+    let output = pack(temp_dir.path(), Format::Json);
+    let document: serde_json::Value = serde_json::from_str(&output).expect("Invalid JSON");
+    assert!(document["description"].is_string());
+
+    let files = document["files"].as_array().unwrap();
+    assert_eq!(files.len(), 2);
+    let a = files
+        .iter()
+        .find(|f| f["path"].as_str().unwrap().ends_with("a.rs"))
+        .unwrap();
+    assert_eq!(a["size"], 23);
+    assert_eq!(a["lines"], 2);
+    assert_eq!(a["extension"], "rs");
+    assert_eq!(a["language"], "rust");
+    assert_eq!(a["content"], "--- b.rs ---\nfn a() {}\n");
+}
+
+#[test]
+fn test_json_lines_format_one_object_per_file() {
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(temp_dir.path().join("a.rs"), "fn a() {}").unwrap();
+    fs::write(temp_dir.path().join("b.rs"), "fn b() {}").unwrap();
+
+    let output = pack(temp_dir.path(), Format::JsonLines);
+    let records: Vec<serde_json::Value> = output
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("Invalid JSON line"))
+        .collect();
+    assert_eq!(records.len(), 2);
+    assert!(records.iter().all(|r| r["language"] == "rust"));
+}
+
+/// Writes `== path ==` before every file, and numbers the files of a pack.
+struct Banner {
+    files: usize,
+    written: usize,
+}
+
+impl OutputFormat for Banner {
+    fn write_header(&mut self, writer: &mut dyn Write, info: &PackInfo) -> io::Result<()> {
+        writeln!(writer, "{} files", self.files)?;
+        for entry in &info.toc {
+            writeln!(writer, "{} at line {}", entry.label, entry.line)?;
+        }
+        Ok(())
+    }
+
+    fn write_file(&mut self, writer: &mut dyn Write, file: &PackedFile) -> io::Result<()> {
+        self.written += 1;
+        writeln!(
+            writer,
+            "== {}. {} ==\n{}",
+            self.written,
+            file.label(),
+            file.content
+        )
+    }
+
+    fn write_footer(&mut self, _writer: &mut dyn Write, _info: &PackInfo) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn content_offset(&self) -> Option<usize> {
+        Some(1)
+    }
+}
+
+#[test]
+fn test_custom_formatter() {
+    let temp_dir = TempDir::new().unwrap();
+    for name in ["a", "b", "c"] {
+        fs::write(
+            temp_dir.path().join(format!("{}.rs", name)),
+            "fn f() {}\n".repeat(10),
+        )
+        .unwrap();
+    }
+    let builder = || {
+        DirectoryProcessor::builder()
+            .format(Format::Json)
+            .formatter(|files| {
+                Box::new(Banner {
+                    files: files.len(),
+                    written: 0,
+                })
+            })
+    };
+
+    let mut pack = Vec::new();
+    builder()
+        .toc(true)
+        .build()
+        .unwrap()
+        .write_to(temp_dir.path(), &mut pack)
+        .unwrap();
+    let pack = String::from_utf8(pack).unwrap();
+    assert!(pack.starts_with("3 files\na.rs at line 6\nb.rs at line 18\n"));
+    let lines: Vec<&str> = pack.lines().collect();
+    assert_eq!(lines[4], "== 1. a.rs ==");
+    assert_eq!(lines[16], "== 2. b.rs ==");
+
+    // Parts get a formatter of their own
+    let output = TempDir::new().unwrap();
+    let summary = builder()
+        .output(output.path().join("pack.txt").to_str().unwrap())
+        .split(SplitLimit::Bytes(150))
+        .build()
+        .unwrap()
+        .run(temp_dir.path())
+        .unwrap();
+    assert_eq!(summary.outputs.len(), 3);
+    for part in &summary.outputs {
+        assert!(fs::read_to_string(part)
+            .unwrap()
+            .starts_with("1 files\n== 1. "));
+    }
+}
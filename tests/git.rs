@@ -0,0 +1,182 @@
+use codepack::{DirectoryProcessor, Error, GitSelection, SortOrder, TreeMode};
+use std::{fs, path::Path, process::Command};
+use tempfile::TempDir;
+
+fn git(dir: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+        .args(args)
+        .status()
+        .unwrap();
+    assert!(status.success(), "git {:?}", args);
+}
+
+/// A repository with a commit on `main`, a commit on `feature`, and changes on top of it:
+/// `src/a.rs` modified, `docs/new.md` staged and `src/untracked.rs` not tracked.
+fn repository() -> TempDir {
+    let temp_dir = TempDir::new().unwrap();
+    let dir = temp_dir.path();
+    fs::create_dir_all(dir.join("src/api")).unwrap();
+    fs::create_dir_all(dir.join("docs")).unwrap();
+    fs::write(dir.join("src/a.rs"), "fn a() {}\n").unwrap();
+    fs::write(dir.join("src/api/b.rs"), "fn b() {}\n").unwrap();
+    fs::write(dir.join("docs/guide.md"), "# Guide\n").unwrap();
+    git(dir, &["init", "-q", "-b", "main"]);
+    git(dir, &["add", "-A"]);
+    git(dir, &["commit", "-q", "-m", "Initial commit"]);
+
+    git(dir, &["checkout", "-q", "-b", "feature"]);
+    fs::write(dir.join("src/api/b.rs"), "fn b() {}\nfn c() {}\n").unwrap();
+    git(dir, &["commit", "-q", "-a", "-m", "Add c"]);
+
+    fs::write(dir.join("src/a.rs"), "fn a() { todo!() }\n").unwrap();
+    fs::write(dir.join("docs/new.md"), "# New\n").unwrap();
+    git(dir, &["add", "docs/new.md"]);
+    fs::write(dir.join("src/untracked.rs"), "fn u() {}\n").unwrap();
+    temp_dir
+}
+
+fn paths(dir: &Path, selections: &[GitSelection]) -> Vec<String> {
+    selections
+        .iter()
+        .fold(DirectoryProcessor::builder(), |builder, selection| {
+            builder.git(selection.clone())
+        })
+        .build()
+        .unwrap()
+        .collect(dir)
+        .unwrap()
+        .iter()
+        .map(|file| file.path.display().to_string().replace('\\', "/"))
+        .collect()
+}
+
+#[test]
+fn test_git_selections() {
+    let repo = repository();
+    let dir = repo.path();
+    // Rewritten with the same contents, so not changed
+    fs::write(dir.join("docs/guide.md"), "# Guide\n").unwrap();
+
+    assert_eq!(
+        paths(dir, &[GitSelection::Changed("HEAD".to_string())]),
+        ["docs/new.md", "src/a.rs", "src/untracked.rs"]
+    );
+    assert_eq!(
+        paths(dir, &[GitSelection::Changed("main".to_string())]),
+        [
+            "docs/new.md",
+            "src/a.rs",
+            "src/api/b.rs",
+            "src/untracked.rs"
+        ]
+    );
+    assert_eq!(paths(dir, &[GitSelection::Staged]), ["docs/new.md"]);
+    assert_eq!(
+        paths(dir, &[GitSelection::Range("main..feature".to_string())]),
+        ["src/api/b.rs"]
+    );
+    assert_eq!(
+        paths(dir, &[GitSelection::Range("feature...main".to_string())]),
+        Vec::<String>::new()
+    );
+    // Selections add up, and paths are relative to the packed directory
+    assert_eq!(
+        paths(
+            &dir.join("src"),
+            &[
+                GitSelection::Staged,
+                GitSelection::Range("main..feature".to_string())
+            ]
+        ),
+        ["api/b.rs"]
+    );
+}
+
+#[test]
+fn test_git_selection_tree_and_errors() {
+    let repo = repository();
+    let dir = repo.path();
+
+    // The full tree still shows the files left out
+    let mut pack = Vec::new();
+    DirectoryProcessor::builder()
+        .git(GitSelection::Range("main..feature".to_string()))
+        .tree(TreeMode::All)
+        .build()
+        .unwrap()
+        .write_to(dir, &mut pack)
+        .unwrap();
+    let pack = String::from_utf8(pack).unwrap();
+    assert!(pack.contains("guide.md"));
+    assert!(pack.contains("fn c() {}"));
+    assert!(!pack.contains("fn a()"));
+
+    let processor = DirectoryProcessor::builder()
+        .git(GitSelection::Changed("no-such-branch".to_string()))
+        .build()
+        .unwrap();
+    assert!(matches!(processor.collect(dir), Err(Error::Git(_))));
+
+    let not_a_repository = TempDir::new().unwrap();
+    let processor = DirectoryProcessor::builder()
+        .git(GitSelection::Staged)
+        .build()
+        .unwrap();
+    assert!(matches!(
+        processor.collect(not_a_repository.path()),
+        Err(Error::Git(_))
+    ));
+
+    for selection in [
+        GitSelection::Range("main".to_string()),
+        GitSelection::Changed("--output=/tmp/x".to_string()),
+    ] {
+        assert!(matches!(
+            DirectoryProcessor::builder().git(selection).build(),
+            Err(Error::InvalidOptions(_))
+        ));
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn test_git_paths_are_not_quoted_or_split() {
+    use std::path::PathBuf;
+
+    let repo = repository();
+    let dir = repo.path();
+    git(dir, &["add", "-A"]);
+    git(dir, &["commit", "-q", "-m", "Commit the changes"]);
+    let odd = [
+        PathBuf::from("caf\u{e9} \"\t\".rs"),
+        PathBuf::from("new\nline.rs"),
+    ];
+    for path in &odd {
+        fs::write(dir.join(path), "fn odd() {}\n").unwrap();
+        git(dir, &["add", "-A"]);
+        git(dir, &["commit", "-q", "-m", "Add an odd name"]);
+    }
+
+    let files = |builder: codepack::DirectoryProcessorBuilder| -> Vec<PathBuf> {
+        builder
+            .extensions(["rs"])
+            .build()
+            .unwrap()
+            .collect(dir)
+            .unwrap()
+            .into_iter()
+            .map(|file| file.path)
+            .collect()
+    };
+    let changed =
+        files(DirectoryProcessor::builder().git(GitSelection::Range("HEAD~2..HEAD".to_string())));
+    assert_eq!(changed.len(), 2);
+    assert!(odd.iter().all(|path| changed.contains(path)));
+
+    // The last commit comes first, then the one before it
+    let recent = files(DirectoryProcessor::builder().sort(SortOrder::GitRecent));
+    assert_eq!(recent[..2], [odd[1].clone(), odd[0].clone()]);
+}
@@ -1,5 +1,5 @@
-use codepack::DirectoryProcessor;
-use std::fs;
+use codepack::{DirectoryProcessor, Error, Filter, SkippedFile, SplitLimit, STDOUT};
+use std::{fs, path::PathBuf};
 use tempfile::TempDir;
 
 #[test]
@@ -12,18 +12,17 @@ fn test_integration() -> Result<(), Box<dyn std::error::Error>> {
     fs::write(&test_file_path, "This is a test file.")?;
 
     // Run the codepack processor
-    let processor = DirectoryProcessor::new(
-        vec!["txt".to_string()],
-        vec![],
-        false,
-        output_file_path.to_str().unwrap().to_string(),
-        false,
-        vec![],
-    );
-    let files_processed = processor.run(temp_dir.path())?;
+    let processor = DirectoryProcessor::builder()
+        .extensions(["txt"])
+        .output(output_file_path.to_str().unwrap())
+        .build()
+        .unwrap();
+    let summary = processor.run(temp_dir.path())?;
 
     // Check the number of files processed
-    println!("Files processed: {}", files_processed);
+    println!("Files processed: {}", summary.files.len());
+    assert_eq!(summary.files.len(), 1);
+    assert!(summary.tokens > summary.files[0].tokens);
 
     // Check the output file
     let output_content = fs::read_to_string(&output_file_path)?;
@@ -32,3 +31,169 @@ fn test_integration() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+#[test]
+fn test_stdout_output() {
+    let processor = DirectoryProcessor::builder()
+        .extensions(["txt"])
+        .output(STDOUT)
+        .build()
+        .unwrap();
+    assert!(processor.writes_to_stdout());
+
+    // Parts need files of their own
+    let result = DirectoryProcessor::builder()
+        .output(STDOUT)
+        .split(SplitLimit::Bytes(10))
+        .build();
+    assert!(matches!(result, Err(Error::InvalidOptions(_))));
+}
+
+#[test]
+fn test_write_to_and_collect() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    fs::write(temp_dir.path().join("a.txt"), "First file.")?;
+    fs::write(temp_dir.path().join("b.txt"), "Second file.")?;
+    let output_file_path = temp_dir.path().join("output.txt");
+
+    let processor = DirectoryProcessor::builder()
+        .extensions(["txt"])
+        .output(output_file_path.to_str().unwrap())
+        .build()
+        .unwrap();
+
+    let mut pack = Vec::new();
+    let summary = processor.write_to(temp_dir.path(), &mut pack)?;
+    let pack = String::from_utf8(pack)?;
+    assert_eq!(summary.files.len(), 2);
+    assert!(summary.outputs.is_empty());
+    assert!(pack.contains("First file.") && pack.contains("Second file."));
+    assert!(!output_file_path.exists());
+
+    let mut files = processor.collect(temp_dir.path())?;
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    assert_eq!(files.len(), 2);
+    assert_eq!(files[0].path, std::path::Path::new("a.txt"));
+    assert_eq!(files[1].content, "Second file.");
+
+    // A failing writer is reported as such, not as the output file
+    let full = std::io::Cursor::new([0u8; 16]);
+    let err = processor.write_to(temp_dir.path(), full).unwrap_err();
+    assert!(matches!(err, Error::Write(_)));
+    assert_eq!(err.to_string(), "I/O error writing the pack");
+
+    Ok(())
+}
+
+#[test]
+fn test_builder_rejects_invalid_glob() {
+    let err = DirectoryProcessor::builder()
+        .excluded_files(["src/[oops"])
+        .build()
+        .unwrap_err();
+    assert!(matches!(err, Error::InvalidGlob { ref pattern, .. } if pattern == "src/[oops"));
+}
+
+#[test]
+fn test_existing_output_is_not_overwritten() {
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(temp_dir.path().join("test.txt"), "This is a test file.").unwrap();
+    let output = temp_dir.path().join("notes.md");
+    fs::write(&output, "Precious notes").unwrap();
+
+    let mut processor = DirectoryProcessor::builder()
+        .extensions(["txt"])
+        .output(output.to_str().unwrap())
+        .build()
+        .unwrap();
+    let err = processor.run(temp_dir.path()).unwrap_err();
+    assert!(matches!(err, Error::OutputExists(ref path) if *path == output));
+
+    let mut prompt = Vec::new();
+    let err = processor
+        .confirm_overwrite(&mut std::io::Cursor::new(b"n\n"), &mut prompt)
+        .unwrap_err();
+    assert!(matches!(err, Error::Cancelled));
+    assert_eq!(fs::read_to_string(&output).unwrap(), "Precious notes");
+
+    processor
+        .confirm_overwrite(&mut std::io::Cursor::new(b"y\n"), &mut prompt)
+        .unwrap();
+    processor.run(temp_dir.path()).unwrap();
+    assert!(fs::read_to_string(&output)
+        .unwrap()
+        .contains("This is a test file."));
+}
+
+#[test]
+fn test_invalid_filter_and_non_utf8_file() {
+    assert!(matches!(
+        "owner=root".parse::<Filter>(),
+        Err(Error::InvalidFilter(ref filter)) if filter == "owner=root"
+    ));
+
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(temp_dir.path().join("latin1.txt"), b"caf\xe9").unwrap();
+    fs::write(temp_dir.path().join("utf8.txt"), "café").unwrap();
+
+    // Skipped and reported by default
+    let processor = DirectoryProcessor::builder()
+        .extensions(["txt"])
+        .encoding("*.txt", "utf-8")
+        .build()
+        .unwrap();
+    let mut pack = Vec::new();
+    let summary = processor.write_to(temp_dir.path(), &mut pack).unwrap();
+    assert_eq!(summary.files.len(), 1);
+    assert_eq!(
+        summary.skipped,
+        [SkippedFile {
+            path: PathBuf::from("latin1.txt"),
+            reason: "not valid UTF-8".to_string(),
+        }]
+    );
+
+    // Fails before writing anything in strict mode
+    let output_dir = TempDir::new().unwrap();
+    let output = output_dir.path().join("pack.txt");
+    let processor = DirectoryProcessor::builder()
+        .extensions(["txt"])
+        .output(output.to_str().unwrap())
+        .encoding("*.txt", "utf-8")
+        .strict(true)
+        .build()
+        .unwrap();
+    let err = processor.run(temp_dir.path()).unwrap_err();
+    assert!(matches!(err, Error::NonUtf8(ref path) if path.ends_with("latin1.txt")));
+    assert!(!output.exists());
+}
+
+#[test]
+fn test_files_are_packed_in_path_order() {
+    let temp_dir = TempDir::new().unwrap();
+    for dir in ["b", "a/z", "a", "c/d/e"] {
+        fs::create_dir_all(temp_dir.path().join(dir)).unwrap();
+        for i in 0..20 {
+            fs::write(temp_dir.path().join(dir).join(format!("{}.txt", i)), dir).unwrap();
+        }
+    }
+
+    let processor = DirectoryProcessor::builder()
+        .extensions(["txt"])
+        .build()
+        .unwrap();
+    let paths: Vec<_> = processor
+        .collect(temp_dir.path())
+        .unwrap()
+        .into_iter()
+        .map(|file| file.path)
+        .collect();
+    assert_eq!(paths.len(), 80);
+    assert!(paths.windows(2).all(|pair| pair[0] < pair[1]));
+
+    let mut first = Vec::new();
+    let mut second = Vec::new();
+    processor.write_to(temp_dir.path(), &mut first).unwrap();
+    processor.write_to(temp_dir.path(), &mut second).unwrap();
+    assert_eq!(first, second);
+}
@@ -0,0 +1,108 @@
+use codepack::{DirectoryProcessor, Error, Format, LineNumbers, Unpacker};
+use std::fs;
+use tempfile::TempDir;
+
+#[test]
+fn test_line_numbers_width_and_separator() {
+    let numbered = LineNumbers::default().apply("fn a() {}\r\n\r\n".repeat(5).as_str());
+    assert!(numbered.starts_with(" 1 | fn a() {}\r\n 2 |\r\n 3 | fn a() {}\r\n"));
+    assert!(numbered.ends_with("10 |\r\n"));
+
+    let numbered = LineNumbers {
+        width: Some(4),
+        separator: ": ".to_string(),
+    }
+    .apply("a\nb");
+    assert_eq!(numbered, "   1: a\n   2: b");
+    assert_eq!(LineNumbers::default().apply(""), "");
+}
+
+#[test]
+fn test_line_numbers_in_pack() {
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(temp_dir.path().join("a.rs"), "fn a() {}\n\nfn b() {}\n").unwrap();
+
+    let processor = DirectoryProcessor::builder()
+        .line_numbers(LineNumbers::default())
+        .build()
+        .unwrap();
+    let files = processor.collect(temp_dir.path()).unwrap();
+    assert_eq!(files[0].content, "1 | fn a() {}\n2 |\n3 | fn b() {}\n");
+    // Metadata describes the file, not its numbered contents
+    assert_eq!((files[0].size, files[0].lines), (21, 3));
+
+    let mut pack = Vec::new();
+    processor.write_to(temp_dir.path(), &mut pack).unwrap();
+    let pack = String::from_utf8(pack).unwrap();
+    assert!(pack.contains("starts with its line number"));
+    assert!(pack.contains("\n1 | fn a() {}\n"));
+
+    for (format, metadata) in [
+        (Format::Text, "- a.rs: line 10, 3 lines, 21 bytes\n"),
+        (Format::Json, "\"size\":21,\"lines\":3,"),
+    ] {
+        let mut pack = Vec::new();
+        DirectoryProcessor::builder()
+            .format(format)
+            .line_numbers(LineNumbers::default())
+            .toc(true)
+            .build()
+            .unwrap()
+            .write_to(temp_dir.path(), &mut pack)
+            .unwrap();
+        assert!(
+            String::from_utf8(pack).unwrap().contains(metadata),
+            "{:?}",
+            format
+        );
+    }
+}
+
+#[test]
+fn test_numbered_pack_unpacks_without_numbers() {
+    let source = TempDir::new().unwrap();
+    let lib = "fn a() {}\r\n\r\n    fn b() {}\n".repeat(4);
+    fs::write(source.path().join("lib.rs"), &lib).unwrap();
+    fs::write(source.path().join("one.rs"), "1 | not a number\n").unwrap();
+
+    for format in [
+        Format::Text,
+        Format::Markdown,
+        Format::Xml,
+        Format::Json,
+        Format::JsonLines,
+    ] {
+        for prompt in [true, false] {
+            let mut pack = Vec::new();
+            DirectoryProcessor::builder()
+                .format(format)
+                .suppress_prompt(!prompt)
+                .line_numbers(LineNumbers {
+                    width: Some(3),
+                    separator: ": ".to_string(),
+                })
+                .build()
+                .unwrap()
+                .write_to(source.path(), &mut pack)
+                .unwrap();
+            let pack = String::from_utf8(pack).unwrap();
+            assert!(pack.contains(" 12:     fn b() {}"), "{:?}", format);
+
+            let destination = TempDir::new().unwrap();
+            Unpacker::new(destination.path()).unpack(&pack).unwrap();
+            for name in ["lib.rs", "one.rs"] {
+                assert_eq!(
+                    fs::read(destination.path().join(name)).unwrap(),
+                    fs::read(source.path().join(name)).unwrap(),
+                    "{} in {:?} (prompt: {})",
+                    name,
+                    format,
+                    prompt
+                );
+            }
+        }
+    }
+
+    let pack = "<files>\n<!-- Every line of a file's contents starts with its line number in the file and ` | `, which are not part of the file. -->\n<file path=\"a.rs\"><![CDATA[1 | a\nb\n]]></file>\n</files>\n";
+    assert!(matches!(Unpacker::parse(pack), Err(Error::InvalidPack(_))));
+}
@@ -0,0 +1,100 @@
+use codepack::{Comparison, DirectoryProcessor, Error, Filter, FilterExpr, Timestamp};
+use std::{
+    fs,
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tempfile::TempDir;
+
+fn set_modified(path: &Path, time: SystemTime) {
+    fs::File::options()
+        .write(true)
+        .open(path)
+        .unwrap()
+        .set_modified(time)
+        .unwrap();
+}
+
+#[test]
+fn test_metadata_filters_select_files() {
+    let temp_dir = TempDir::new().unwrap();
+    let dir = temp_dir.path();
+    fs::write(dir.join("small.rs"), "fn a() {}\n").unwrap();
+    fs::write(dir.join("long.rs"), "// line\n".repeat(30)).unwrap();
+    fs::write(dir.join("old.rs"), "fn old() {}\n").unwrap();
+    // 2020-01-01 12:00 UTC
+    set_modified(
+        &dir.join("old.rs"),
+        UNIX_EPOCH + Duration::from_secs(1_577_880_000),
+    );
+    set_modified(
+        &dir.join("small.rs"),
+        SystemTime::now() - Duration::from_secs(3 * 24 * 60 * 60),
+    );
+
+    let paths = |filter: &str| -> Vec<String> {
+        DirectoryProcessor::builder()
+            .filters([filter.parse::<FilterExpr>().unwrap()])
+            .build()
+            .unwrap()
+            .collect(dir)
+            .unwrap()
+            .iter()
+            .map(|file| file.path.display().to_string())
+            .collect()
+    };
+    assert_eq!(paths("size<100"), ["old.rs", "small.rs"]);
+    assert_eq!(paths("size>=1k OR lines>20"), ["long.rs"]);
+    assert_eq!(paths("lines<=1 AND NOT file.name=old"), ["small.rs"]);
+    assert_eq!(paths("modified<7d"), ["long.rs", "small.rs"]);
+    assert_eq!(paths("modified<1d"), ["long.rs"]);
+    assert_eq!(paths("modified=2020-01-01"), ["old.rs"]);
+    assert_eq!(paths("modified>2020-01-01"), ["long.rs", "small.rs"]);
+    assert_eq!(paths("modified<2019-12-31"), Vec::<String>::new());
+}
+
+#[test]
+fn test_metadata_filter_parsing() {
+    assert_eq!(
+        "size<100k".parse::<Filter>().unwrap(),
+        Filter::Size(Comparison::Less, 100 * 1024)
+    );
+    assert_eq!(
+        "lines>=20".parse::<Filter>().unwrap(),
+        Filter::Lines(Comparison::GreaterOrEqual, 20)
+    );
+    let filter: Filter = "modified>2026-09-01".parse().unwrap();
+    assert_eq!(
+        filter,
+        Filter::Modified(
+            Comparison::Greater,
+            Timestamp::Date {
+                year: 2026,
+                month: 9,
+                day: 1
+            }
+        )
+    );
+    assert_eq!(filter.to_string(), "modified>2026-09-01");
+    assert_eq!(
+        "modified<14d".parse::<Filter>().unwrap().to_string(),
+        "modified<2w"
+    );
+
+    for filter in [
+        "size<10q",
+        "size<20lines",
+        "lines>many",
+        "modified>2026-02-30",
+        "modified=7d",
+    ] {
+        assert!(
+            matches!(
+                filter.parse::<Filter>(),
+                Err(Error::InvalidFilterValue { .. })
+            ),
+            "{}",
+            filter
+        );
+    }
+}
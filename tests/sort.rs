@@ -0,0 +1,82 @@
+use codepack::{DirectoryProcessor, Error, SortOrder};
+use std::{fs, path::Path};
+use tempfile::TempDir;
+
+fn setup() -> TempDir {
+    let temp_dir = TempDir::new().unwrap();
+    fs::create_dir_all(temp_dir.path().join("src/util")).unwrap();
+    fs::write(
+        temp_dir.path().join("README.md"),
+        "# Readme, the largest file",
+    )
+    .unwrap();
+    fs::write(
+        temp_dir.path().join("Cargo.toml"),
+        "[package]\nname = \"x\"",
+    )
+    .unwrap();
+    fs::write(temp_dir.path().join("src/z.rs"), "fn z() {}").unwrap();
+    fs::write(temp_dir.path().join("src/util/a.rs"), "a").unwrap();
+    temp_dir
+}
+
+fn order(dir: &Path, sort: SortOrder, first: &[&str]) -> Vec<String> {
+    DirectoryProcessor::builder()
+        .sort(sort)
+        .sort_first(first.iter().copied())
+        .build()
+        .unwrap()
+        .collect(dir)
+        .unwrap()
+        .into_iter()
+        .map(|file| file.path.to_string_lossy().replace('\\', "/"))
+        .collect()
+}
+
+#[test]
+fn test_sort_orders() {
+    let temp_dir = setup();
+
+    assert_eq!(
+        order(temp_dir.path(), SortOrder::Path, &[]),
+        ["Cargo.toml", "README.md", "src/util/a.rs", "src/z.rs"]
+    );
+    assert_eq!(
+        order(temp_dir.path(), SortOrder::DepthFirst, &[]),
+        ["Cargo.toml", "README.md", "src/z.rs", "src/util/a.rs"]
+    );
+    assert_eq!(
+        order(temp_dir.path(), SortOrder::Size, &[]),
+        ["src/util/a.rs", "src/z.rs", "Cargo.toml", "README.md"]
+    );
+    assert_eq!(
+        order(temp_dir.path(), SortOrder::Path, &["README*", "*.toml"]),
+        ["README.md", "Cargo.toml", "src/util/a.rs", "src/z.rs"]
+    );
+
+    let err = DirectoryProcessor::builder()
+        .sort_first(["[oops"])
+        .build()
+        .unwrap_err();
+    assert!(matches!(err, Error::InvalidGlob { .. }));
+}
+
+#[test]
+fn test_identical_input_gives_identical_pack() {
+    let first = setup();
+    let second = setup();
+    // Created in another order, so the directory entries are likely in another order too
+    fs::write(second.path().join("src/z.rs"), "fn z() {}").unwrap();
+
+    let pack = |dir: &Path| {
+        let mut pack = Vec::new();
+        DirectoryProcessor::builder()
+            .build()
+            .unwrap()
+            .write_to(dir, &mut pack)
+            .unwrap();
+        pack
+    };
+    assert_eq!(pack(first.path()), pack(second.path()));
+    assert_eq!(pack(first.path()), pack(first.path()));
+}
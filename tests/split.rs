@@ -0,0 +1,173 @@
+use codepack::{part_path, DirectoryProcessor, Error, Format, SplitLimit, Unpacker};
+use std::{fs, path::Path};
+use tempfile::TempDir;
+
+fn pack(dir: &Path, output: &Path, format: Format, limit: SplitLimit) -> codepack::PackSummary {
+    DirectoryProcessor::builder()
+        .extensions(["rs"])
+        .output(output.to_str().unwrap())
+        .force(true)
+        .format(format)
+        .split(limit)
+        .build()
+        .unwrap()
+        .run(dir)
+        .expect("Failed to run processor")
+}
+
+#[test]
+fn test_split_bytes_into_parts_and_unpack() {
+    let source = TempDir::new().unwrap();
+    fs::write(source.path().join("a.rs"), "fn a() {}\n".repeat(30)).unwrap();
+    fs::write(source.path().join("b.rs"), "fn b() {}\n".repeat(30)).unwrap();
+    // Too large for a single part, so it is split at line boundaries
+    let large: String = (0..400).map(|i| format!("let x{} = {};\n", i, i)).collect();
+    fs::write(source.path().join("large.rs"), &large).unwrap();
+
+    for format in [Format::Text, Format::Markdown, Format::Xml, Format::Json] {
+        let output = TempDir::new().unwrap();
+        let summary = pack(
+            source.path(),
+            &output.path().join("pack.txt"),
+            format,
+            SplitLimit::Bytes(2000),
+        );
+        assert!(summary.outputs.len() > 3, "{:?}", format);
+        assert!(!output.path().join("pack.txt").exists());
+
+        let destination = output.path().join("out");
+        for (i, part) in summary.outputs.iter().enumerate() {
+            assert_eq!(part, &part_path(&output.path().join("pack.txt"), i + 1));
+            let content = fs::read_to_string(part).unwrap();
+            assert!(content.len() <= 2000, "{:?} part {}", format, i + 1);
+            let total = summary.outputs.len();
+            assert!(
+                content.contains(&format!("part {} of {}", i + 1, total))
+                    || content.contains(&format!(
+                        "\"part\":{{\"number\":{},\"total\":{}",
+                        i + 1,
+                        total
+                    ))
+            );
+
+            Unpacker::new(&destination).unpack(&content).unwrap();
+        }
+
+        assert_eq!(
+            fs::read_to_string(destination.join("large.rs")).unwrap(),
+            large,
+            "{:?}",
+            format
+        );
+        assert_eq!(
+            fs::read_to_string(destination.join("a.rs")).unwrap(),
+            "fn a() {}\n".repeat(30)
+        );
+    }
+}
+
+#[test]
+fn test_split_tokens_small_pack_stays_whole() {
+    let source = TempDir::new().unwrap();
+    fs::write(source.path().join("a.rs"), "fn a() {}\n").unwrap();
+    let output = source.path().join("pack.txt");
+
+    let summary = pack(
+        source.path(),
+        &output,
+        Format::Text,
+        SplitLimit::Tokens(10_000),
+    );
+    assert_eq!(summary.outputs.len(), 1);
+    assert_eq!(summary.outputs[0], output);
+    assert!(!fs::read_to_string(output).unwrap().contains("part 1 of"));
+}
+
+#[test]
+fn test_part_path() {
+    assert_eq!(
+        part_path(Path::new("out/code_pack.txt"), 2),
+        Path::new("out/code_pack.part2.txt")
+    );
+    assert_eq!(part_path(Path::new("pack"), 10), Path::new("pack.part10"));
+}
+
+#[test]
+fn test_split_parts_are_not_overwritten() {
+    let source = TempDir::new().unwrap();
+    fs::write(source.path().join("a.rs"), "fn a() {}\n".repeat(100)).unwrap();
+    fs::write(source.path().join("b.rs"), "fn b() {}\n".repeat(100)).unwrap();
+    let output = TempDir::new().unwrap();
+    let pack_path = output.path().join("p.txt");
+    let run = |limit: usize| {
+        DirectoryProcessor::builder()
+            .extensions(["rs"])
+            .output(pack_path.to_str().unwrap())
+            .split(SplitLimit::Bytes(limit))
+            .build()
+            .unwrap()
+            .run(source.path())
+    };
+
+    // A part holding other data is left alone
+    fs::write(part_path(&pack_path, 1), "precious").unwrap();
+    match run(1500) {
+        Err(Error::OutputExists(path)) => assert_eq!(path, part_path(&pack_path, 1)),
+        other => panic!("{:?}", other),
+    }
+    assert_eq!(
+        fs::read_to_string(part_path(&pack_path, 1)).unwrap(),
+        "precious"
+    );
+    assert!(!part_path(&pack_path, 2).exists());
+
+    // Parts of a previous pack are replaced, and those past the new count removed
+    fs::remove_file(part_path(&pack_path, 1)).unwrap();
+    let parts = run(1500).unwrap().outputs.len();
+    assert!(parts > 1);
+    assert_eq!(
+        run(100_000).unwrap().outputs,
+        std::slice::from_ref(&pack_path)
+    );
+    for number in 1..=parts {
+        assert!(!part_path(&pack_path, number).exists());
+    }
+
+    // Unless they hold other data
+    fs::write(part_path(&pack_path, 1), "precious").unwrap();
+    assert!(matches!(run(100_000), Err(Error::OutputExists(_))));
+}
+
+#[test]
+fn test_split_limit_too_small() {
+    let source = TempDir::new().unwrap();
+    fs::write(source.path().join("a.rs"), "fn a() {}\n".repeat(30)).unwrap();
+    let output = TempDir::new().unwrap();
+    let run = |limit: SplitLimit| {
+        DirectoryProcessor::builder()
+            .extensions(["rs"])
+            .output(output.path().join("pack.txt").to_str().unwrap())
+            .split(limit)
+            .build()
+            .unwrap()
+            .run(source.path())
+    };
+
+    // Not even the preamble of a part fits
+    assert!(matches!(
+        run(SplitLimit::Bytes(10)),
+        Err(Error::InvalidOptions(_))
+    ));
+    assert!(matches!(
+        run(SplitLimit::Tokens(5)),
+        Err(Error::InvalidOptions(_))
+    ));
+
+    // A line longer than a part cannot be split
+    fs::write(source.path().join("long.rs"), "x".repeat(5000)).unwrap();
+    match run(SplitLimit::Bytes(2000)) {
+        Err(Error::InvalidOptions(reason)) => assert!(reason.contains("long.rs"), "{}", reason),
+        other => panic!("{:?}", other),
+    }
+    assert_eq!(fs::read_dir(output.path()).unwrap().count(), 0);
+}
@@ -0,0 +1,155 @@
+use codepack::{DirectoryProcessor, Format, TreeMode};
+use std::fs;
+use tempfile::TempDir;
+
+fn setup() -> TempDir {
+    let temp_dir = TempDir::new().unwrap();
+    fs::create_dir_all(temp_dir.path().join("src")).unwrap();
+    fs::write(temp_dir.path().join("src/a.rs"), "fn a() {}\n\nfn b() {}\n").unwrap();
+    fs::write(temp_dir.path().join("src/b.rs"), "fn c() {}").unwrap();
+    fs::write(temp_dir.path().join("src/c.rs"), "").unwrap();
+    fs::write(temp_dir.path().join("src/d.rs"), "fn d() {}\n".repeat(40)).unwrap();
+    temp_dir
+}
+
+/// Reads `(path, line, lines, size)` back from a plain text table of contents.
+fn entries(pack: &str) -> Vec<(String, usize, usize, u64)> {
+    pack.lines()
+        .filter_map(|line| {
+            let (path, rest) = line.strip_prefix("- ")?.split_once(": line ")?;
+            let mut numbers = rest.split(", ").map(|n| n.split(' ').next().unwrap());
+            Some((
+                path.to_string(),
+                numbers.next()?.parse().ok()?,
+                numbers.next()?.parse().ok()?,
+                numbers.next()?.parse().ok()?,
+            ))
+        })
+        .collect()
+}
+
+#[test]
+fn test_toc_points_at_file_contents() {
+    let temp_dir = setup();
+
+    for format in [Format::Text, Format::Markdown] {
+        for tree in [TreeMode::Off, TreeMode::All] {
+            let mut pack = Vec::new();
+            DirectoryProcessor::builder()
+                .format(format)
+                .tree(tree)
+                .toc(true)
+                .build()
+                .unwrap()
+                .write_to(temp_dir.path(), &mut pack)
+                .unwrap();
+            let pack = String::from_utf8(pack).unwrap();
+            let lines: Vec<&str> = pack.lines().collect();
+
+            let entries = entries(&pack);
+            assert_eq!(entries.len(), 4, "{:?}", format);
+            assert_eq!(pack.matches("Table of contents").count(), 1);
+            for (path, line, count, size) in entries {
+                let content = fs::read_to_string(temp_dir.path().join(&path)).unwrap();
+                assert_eq!(content.lines().count(), count);
+                assert_eq!(content.len() as u64, size);
+                if let Some(first) = content.lines().next() {
+                    assert_eq!(lines[line - 1], first, "{:?} {}", format, path);
+                }
+                // The line before the contents names the file
+                let before = if format == Format::Text { 1 } else { 3 };
+                assert!(lines[line - 1 - before].ends_with(&path));
+            }
+        }
+    }
+}
+
+#[test]
+fn test_toc_in_xml_and_split_parts() {
+    let temp_dir = setup();
+    let mut pack = Vec::new();
+    DirectoryProcessor::builder()
+        .format(Format::Xml)
+        .toc(true)
+        .build()
+        .unwrap()
+        .write_to(temp_dir.path(), &mut pack)
+        .unwrap();
+    let pack = String::from_utf8(pack).unwrap();
+    let toc = pack
+        .lines()
+        .find(|line| line.starts_with("<toc path=\"src/b.rs\""))
+        .unwrap();
+    let line: usize = toc
+        .split("line=\"")
+        .nth(1)
+        .unwrap()
+        .split('"')
+        .next()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert_eq!(
+        pack.lines().nth(line - 1).unwrap(),
+        "<file path=\"src/b.rs\"><![CDATA[fn c() {}]]></file>"
+    );
+
+    // Every part lists the files it holds, numbered within the part
+    let output = TempDir::new().unwrap();
+    let summary = DirectoryProcessor::builder()
+        .output(output.path().join("pack.txt").to_str().unwrap())
+        .toc(true)
+        .split(codepack::SplitLimit::Bytes(1300))
+        .build()
+        .unwrap()
+        .run(temp_dir.path())
+        .unwrap();
+    assert!(summary.outputs.len() > 1);
+    for part in &summary.outputs {
+        let part = fs::read_to_string(part).unwrap();
+        let lines: Vec<&str> = part.lines().collect();
+        let entries = entries(&part);
+        assert!(!entries.is_empty());
+        for (_, line, _, _) in entries {
+            assert!(lines[line - 2].starts_with("--codepack-"));
+        }
+    }
+}
+
+#[test]
+fn test_toc_of_split_parts_lists_only_their_files() {
+    let temp_dir = TempDir::new().unwrap();
+    for i in 0..150 {
+        fs::write(
+            temp_dir.path().join(format!("module_{:03}.rs", i)),
+            format!("fn f{}() {{}}\n", i),
+        )
+        .unwrap();
+    }
+
+    for (format, limit) in [
+        (Format::Text, 3000),
+        (Format::Markdown, 3000),
+        (Format::Xml, 4000),
+    ] {
+        let output = TempDir::new().unwrap();
+        let summary = DirectoryProcessor::builder()
+            .output(output.path().join("pack").to_str().unwrap())
+            .format(format)
+            .toc(true)
+            .split(codepack::SplitLimit::Bytes(limit))
+            .build()
+            .unwrap()
+            .run(temp_dir.path())
+            .unwrap();
+
+        assert!(summary.outputs.len() > 2, "{:?}", format);
+        let mut listed = 0;
+        for part in &summary.outputs {
+            let part = fs::read_to_string(part).unwrap();
+            assert!(part.len() <= limit, "{:?}: {} bytes", format, part.len());
+            listed += part.matches(": line ").count() + part.matches("<toc ").count();
+        }
+        assert_eq!(listed, 150, "{:?}", format);
+    }
+}
@@ -0,0 +1,81 @@
+use codepack::{DirectoryProcessor, Format, TokenBudget, TokenizerKind};
+use std::fs;
+use tempfile::TempDir;
+
+#[test]
+fn test_tokenizers_count_tokens() {
+    assert_eq!(TokenizerKind::Estimate.tokenizer().count("hello world"), 3);
+    assert_eq!(TokenizerKind::O200k.tokenizer().count("hello world"), 2);
+    assert_eq!(TokenizerKind::Cl100k.tokenizer().count("hello world"), 2);
+    assert_eq!(TokenizerKind::Estimate.tokenizer().count(""), 0);
+
+    assert_eq!("cl100k".parse(), Ok(TokenizerKind::Cl100k));
+    assert!("gpt2".parse::<TokenizerKind>().is_err());
+}
+
+#[test]
+fn test_summary_counts_tokens_per_file_and_pack() {
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(temp_dir.path().join("small.rs"), "fn a() {}").unwrap();
+    fs::write(temp_dir.path().join("large.rs"), "fn b() {}\n".repeat(100)).unwrap();
+    let output = temp_dir.path().join("output.txt");
+
+    let summary = DirectoryProcessor::builder()
+        .extensions(["rs"])
+        .output(output.to_str().unwrap())
+        .force(true)
+        .tokenizer(TokenizerKind::Estimate)
+        .build()
+        .unwrap()
+        .run(temp_dir.path())
+        .expect("Failed to run processor");
+
+    let pack = fs::read_to_string(output).unwrap();
+    // The preamble, the closing delimiter, and the contents and delimiters of both files are
+    // rounded up separately
+    let estimate = pack.chars().count().div_ceil(4);
+    assert!((estimate..estimate + 8).contains(&summary.tokens));
+
+    let file_tokens: usize = summary.files.iter().map(|f| f.tokens).sum();
+    assert!(file_tokens < summary.tokens);
+
+    let largest = summary.largest_files(1);
+    assert_eq!(largest.len(), 1);
+    assert!(largest[0].path.ends_with("large.rs"));
+    assert_eq!(largest[0].size, 1000);
+}
+
+#[test]
+fn test_exact_tokenizer_only_when_needed() {
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(
+        temp_dir.path().join("digits.rs"),
+        "1234567890\n".repeat(100),
+    )
+    .unwrap();
+
+    let tokens = |tokenizer: Option<TokenizerKind>, budget: bool| {
+        let mut builder = DirectoryProcessor::builder()
+            .extensions(["rs"])
+            .format(Format::Markdown);
+        if let Some(tokenizer) = tokenizer {
+            builder = builder.tokenizer(tokenizer);
+        }
+        if budget {
+            builder = builder.token_budget(TokenBudget::new(1_000_000));
+        }
+        builder
+            .build()
+            .unwrap()
+            .write_to(temp_dir.path(), Vec::new())
+            .unwrap()
+            .tokens
+    };
+
+    assert_eq!(
+        tokens(None, false),
+        tokens(Some(TokenizerKind::Estimate), false)
+    );
+    assert_eq!(tokens(None, true), tokens(Some(TokenizerKind::O200k), true));
+    assert_ne!(tokens(None, false), tokens(None, true));
+}
@@ -0,0 +1,97 @@
+use codepack::{DirectoryProcessor, Format, TreeMode, Unpacker};
+use std::{fs, path::Path, process::Command};
+use tempfile::TempDir;
+
+fn setup() -> TempDir {
+    let temp_dir = TempDir::new().unwrap();
+    fs::create_dir_all(temp_dir.path().join("src/util")).unwrap();
+    fs::write(temp_dir.path().join("src/main.rs"), "fn main() {}\n").unwrap();
+    fs::write(
+        temp_dir.path().join("src/util/mod.rs"),
+        "pub fn util() {}\n",
+    )
+    .unwrap();
+    fs::write(temp_dir.path().join("notes.txt"), "not code\n").unwrap();
+    temp_dir
+}
+
+fn pack(dir: &Path, format: Format, tree: TreeMode, suppress_prompt: bool) -> String {
+    let mut pack = Vec::new();
+    DirectoryProcessor::builder()
+        .extensions(["rs"])
+        .format(format)
+        .tree(tree)
+        .suppress_prompt(suppress_prompt)
+        .build()
+        .unwrap()
+        .write_to(dir, &mut pack)
+        .unwrap();
+    String::from_utf8(pack).unwrap()
+}
+
+#[test]
+fn test_tree_after_preamble() {
+    let temp_dir = setup();
+
+    let included = pack(temp_dir.path(), Format::Text, TreeMode::Included, false);
+    let tree = ".\n└── src\n    ├── main.rs\n    └── util\n        └── mod.rs\n";
+    let start = included.find("Directory structure:\n").unwrap();
+    assert!(included[start..].contains(tree));
+    assert!(start < included.find("\n--codepack-").unwrap());
+
+    let all = pack(temp_dir.path(), Format::Text, TreeMode::All, false);
+    assert!(all.contains(".\n├── notes.txt (excluded)\n└── src\n    ├── main.rs\n"));
+    assert!(!all.contains("not code"));
+
+    let off = pack(temp_dir.path(), Format::Text, TreeMode::Off, false);
+    assert!(!off.contains("Directory structure"));
+}
+
+#[test]
+fn test_packs_with_tree_still_unpack() {
+    let temp_dir = setup();
+
+    for format in [Format::Text, Format::Markdown, Format::Xml, Format::Json] {
+        for suppress_prompt in [false, true] {
+            let pack = pack(temp_dir.path(), format, TreeMode::All, suppress_prompt);
+            let files = Unpacker::parse(&pack).unwrap();
+            let paths: Vec<_> = files.iter().map(|file| file.path.as_path()).collect();
+            assert_eq!(
+                paths,
+                [Path::new("src/main.rs"), Path::new("src/util/mod.rs")],
+                "{:?}, suppress_prompt: {}",
+                format,
+                suppress_prompt
+            );
+            assert_eq!(files[0].content, "fn main() {}\n");
+        }
+    }
+}
+
+#[test]
+fn test_bare_tree_flag_before_directory() {
+    let temp_dir = setup();
+    let output = Command::new(env!("CARGO_BIN_EXE_codepack"))
+        .args(["-e", "rs", "-o", "-", "--tree"])
+        .arg(temp_dir.path())
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let pack = String::from_utf8(output.stdout).unwrap();
+    assert!(pack.contains("Directory structure:\n"));
+    assert!(!pack.contains("notes.txt"));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_codepack"))
+        .args(["-e", "rs", "-o", "-", "--tree=all"])
+        .arg(temp_dir.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .contains("notes.txt (excluded)"));
+}
@@ -0,0 +1,125 @@
+use codepack::{
+    DirectoryProcessor, Error, FileSizeLimit, Format, LineNumbers, TruncateStrategy, Unpacker,
+};
+use std::fs;
+use tempfile::TempDir;
+
+fn setup() -> TempDir {
+    let temp_dir = TempDir::new().unwrap();
+    let lib: String = (1..=30)
+        .map(|i| format!("fn f{}() {{\n    {}\n}}\n", i, i))
+        .collect();
+    fs::write(temp_dir.path().join("lib.rs"), lib).unwrap();
+    fs::write(temp_dir.path().join("small.rs"), "fn small() {}\n").unwrap();
+    temp_dir
+}
+
+fn content(temp_dir: &TempDir, limit: FileSizeLimit, strategy: TruncateStrategy) -> String {
+    let files = DirectoryProcessor::builder()
+        .max_file_size(limit, strategy)
+        .line_numbers(LineNumbers::default())
+        .build()
+        .unwrap()
+        .collect(temp_dir.path())
+        .unwrap();
+    assert_eq!(files.last().unwrap().content, "1 | fn small() {}\n");
+    assert!(files[0].truncated && !files.last().unwrap().truncated);
+    let lib = fs::read_to_string(temp_dir.path().join("lib.rs")).unwrap();
+    assert_eq!((files[0].size, files[0].lines), (lib.len() as u64, 90));
+    files[0].content.clone()
+}
+
+#[test]
+fn test_file_size_limit_from_str() {
+    assert_eq!("300".parse(), Ok(FileSizeLimit::Bytes(300)));
+    assert_eq!("2KB".parse(), Ok(FileSizeLimit::Bytes(2048)));
+    assert_eq!("1m".parse(), Ok(FileSizeLimit::Bytes(1 << 20)));
+    assert_eq!("500lines".parse(), Ok(FileSizeLimit::Lines(500)));
+    assert!("12 parsecs".parse::<FileSizeLimit>().is_err());
+}
+
+#[test]
+fn test_oversized_files_are_skipped() {
+    let temp_dir = setup();
+    let mut pack = Vec::new();
+    let summary = DirectoryProcessor::builder()
+        .max_file_size(FileSizeLimit::Bytes(100), TruncateStrategy::Skip)
+        .build()
+        .unwrap()
+        .write_to(temp_dir.path(), &mut pack)
+        .unwrap();
+    assert_eq!(summary.files.len(), 1);
+    assert_eq!(summary.skipped[0].reason, "larger than 100 bytes");
+}
+
+#[test]
+fn test_truncation_strategies() {
+    let temp_dir = setup();
+
+    let head = content(&temp_dir, FileSizeLimit::Lines(4), TruncateStrategy::Head);
+    assert_eq!(
+        head,
+        " 1 | fn f1() {\n 2 |     1\n 3 | }\n 4 | fn f2() {\n[... 86 lines truncated by codepack ...]\n"
+    );
+
+    let head_tail = content(
+        &temp_dir,
+        FileSizeLimit::Lines(4),
+        TruncateStrategy::HeadTail,
+    );
+    assert_eq!(
+        head_tail,
+        " 1 | fn f1() {\n 2 |     1\n[... 86 lines truncated by codepack ...]\n89 |     30\n90 | }\n"
+    );
+
+    let signatures = content(
+        &temp_dir,
+        FileSizeLimit::Lines(2),
+        TruncateStrategy::Signatures,
+    );
+    assert!(signatures.ends_with("\n 1 | fn f1() {\n 4 | fn f2() {\n"));
+}
+
+#[test]
+fn test_truncated_files_are_marked_and_not_unpacked() {
+    let temp_dir = setup();
+    for (format, marker) in [
+        (Format::Text, " lib.rs (truncated)\n"),
+        (Format::Markdown, "## lib.rs (truncated)\n"),
+        (Format::Xml, "<file path=\"lib.rs\" truncated=\"true\">"),
+        (Format::Json, "\"truncated\":true"),
+        (Format::JsonLines, "\"truncated\":true"),
+    ] {
+        let mut pack = Vec::new();
+        DirectoryProcessor::builder()
+            .format(format)
+            .max_file_size(FileSizeLimit::Lines(4), TruncateStrategy::HeadTail)
+            .line_numbers(LineNumbers::default())
+            .build()
+            .unwrap()
+            .write_to(temp_dir.path(), &mut pack)
+            .unwrap();
+        let pack = String::from_utf8(pack).unwrap();
+        assert_eq!(pack.matches(marker).count(), 1, "{:?}: {}", format, pack);
+
+        let destination = TempDir::new().unwrap();
+        let result = Unpacker::new(destination.path()).unpack(&pack);
+        assert!(matches!(result, Err(Error::InvalidPack(_))), "{:?}", format);
+        assert!(!destination.path().join("small.rs").exists());
+
+        let written = Unpacker::new(destination.path())
+            .skip_truncated(true)
+            .unpack(&pack)
+            .unwrap();
+        assert_eq!(
+            written,
+            [destination.path().join("small.rs")],
+            "{:?}",
+            format
+        );
+        assert_eq!(
+            fs::read_to_string(destination.path().join("small.rs")).unwrap(),
+            "fn small() {}\n"
+        );
+    }
+}
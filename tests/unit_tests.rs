@@ -3,69 +3,53 @@ use std::path::Path;
 
 #[test]
 fn test_should_process_file_empty_path() {
-    let processor = DirectoryProcessor::new(vec![], vec![], false, String::new(), false, vec![]);
+    let processor = DirectoryProcessor::builder().build().unwrap();
     let path = Path::new("");
     assert!(!processor.should_process_file(path));
 }
 
 #[test]
 fn test_should_process_file_regular_file() {
-    let processor = DirectoryProcessor::new(vec![], vec![], false, String::new(), false, vec![]);
+    let processor = DirectoryProcessor::builder().build().unwrap();
     let path = Path::new("test.txt");
     assert!(processor.should_process_file(path));
 }
 
 #[test]
 fn test_should_process_file_included_extension() {
-    let processor = DirectoryProcessor::new(
-        vec!["rs".to_string()],
-        vec![],
-        false,
-        String::new(),
-        false,
-        vec![],
-    );
+    let processor = DirectoryProcessor::builder()
+        .extensions(["rs"])
+        .build()
+        .unwrap();
     let path = Path::new("main.rs");
     assert!(processor.should_process_file(path));
 }
 #[test]
 fn test_should_process_file_excluded_file() {
-    let processor = DirectoryProcessor::new(
-        vec![],
-        vec!["main.rs".to_string()],
-        false,
-        String::new(),
-        false,
-        vec![],
-    );
+    let processor = DirectoryProcessor::builder()
+        .excluded_files(["main.rs"])
+        .build()
+        .unwrap();
     let path = Path::new("main.rs");
     assert!(!processor.should_process_file(path));
 }
 
 #[test]
 fn test_should_process_file_excluded_file_by_name() {
-    let processor = DirectoryProcessor::new(
-        vec![],
-        vec!["status".to_string()],
-        false,
-        String::new(),
-        false,
-        vec![],
-    );
+    let processor = DirectoryProcessor::builder()
+        .excluded_files(["status"])
+        .build()
+        .unwrap();
     let path = Path::new("/home/json/code/src/status");
     assert!(!processor.should_process_file(path));
 }
 
 #[test]
 fn test_should_process_file_excluded_globed_file() {
-    let processor = DirectoryProcessor::new(
-        vec![],
-        vec!["*.py".to_string()],
-        false,
-        String::new(),
-        false,
-        vec![],
-    );
+    let processor = DirectoryProcessor::builder()
+        .excluded_files(["*.py"])
+        .build()
+        .unwrap();
     let path = Path::new("script.py");
     assert!(!processor.should_process_file(path));
 }
@@ -80,14 +64,11 @@ fn test_validate_output_file() {
     let mut mock_input = std::io::Cursor::new(b"n\n");
     let mut mock_output = Vec::new();
 
-    let processor = DirectoryProcessor::new(
-        vec!["txt".to_string()],
-        vec![],
-        false,
-        output_path.clone(),
-        false,
-        vec![],
-    );
+    let processor = DirectoryProcessor::builder()
+        .extensions(["txt"])
+        .output(output_path.clone())
+        .build()
+        .unwrap();
 
     // if user chooses not to overwrite, should return false
     assert!(!processor
@@ -115,14 +96,11 @@ fn test_run_and_validate_output_file() {
         .unwrap()
         .to_string();
 
-    let processor = DirectoryProcessor::new(
-        vec!["txt".to_string()],
-        vec![],
-        false,
-        output_path.clone(),
-        false,
-        vec![],
-    );
+    let processor = DirectoryProcessor::builder()
+        .extensions(["txt"])
+        .output(output_path.clone())
+        .build()
+        .unwrap();
 
     processor
         .run(temp_dir.path())
@@ -136,3 +114,62 @@ fn test_run_and_validate_output_file() {
         .validate_output_file(output_path, false, &mut mock_input, &mut mock_output)
         .expect("Failed to validate output file"));
 }
+
+#[test]
+fn test_validate_output_file_recognizes_packs_without_preamble() {
+    use codepack::Format;
+
+    let source = tempfile::TempDir::new().expect("Failed to create temp dir");
+    std::fs::write(source.path().join("a.txt"), "alpha\n").unwrap();
+    let out_dir = tempfile::TempDir::new().expect("Failed to create temp dir");
+
+    for (format, suppress_prompt) in [
+        (Format::JsonLines, false),
+        (Format::Text, true),
+        (Format::Xml, true),
+        (Format::Json, true),
+    ] {
+        let output_path = out_dir
+            .path()
+            .join(format!("pack-{:?}", format))
+            .to_str()
+            .unwrap()
+            .to_string();
+        let processor = DirectoryProcessor::builder()
+            .extensions(["txt"])
+            .format(format)
+            .suppress_prompt(suppress_prompt)
+            .output(output_path.clone())
+            .build()
+            .unwrap();
+
+        // Packing twice replaces the first pack without asking
+        processor
+            .run(source.path())
+            .expect("Failed to run processor");
+        processor
+            .run(source.path())
+            .expect("Failed to replace pack");
+
+        let mut mock_input = std::io::Cursor::new(b"n\n");
+        let mut mock_output = Vec::new();
+        assert!(processor
+            .validate_output_file(output_path, false, &mut mock_input, &mut mock_output)
+            .expect("Failed to validate output file"));
+        assert!(mock_output.is_empty(), "{:?} asked to overwrite", format);
+    }
+
+    // Other JSON Lines are not packs
+    let output_path = out_dir.path().join("other.jsonl");
+    std::fs::write(&output_path, "{\"path\":\"a\"}\n").unwrap();
+    let processor = DirectoryProcessor::builder().build().unwrap();
+    let mut mock_input = std::io::Cursor::new(b"n\n");
+    assert!(!processor
+        .validate_output_file(
+            output_path.to_str().unwrap().to_string(),
+            false,
+            &mut mock_input,
+            &mut Vec::new()
+        )
+        .expect("Failed to validate output file"));
+}
@@ -0,0 +1,148 @@
+use codepack::{DirectoryProcessor, Error, Format, Unpacker};
+use std::{fs, path::Path};
+use tempfile::TempDir;
+
+fn pack(dir: &Path, output: &Path, format: Format) {
+    DirectoryProcessor::builder()
+        .extensions(["rs"])
+        .output(output.to_str().unwrap())
+        .force(true)
+        .format(format)
+        .build()
+        .unwrap()
+        .run(dir)
+        .expect("Failed to run processor");
+}
+
+#[test]
+fn test_unpack_round_trips_every_format() {
+    for format in [
+        Format::Text,
+        Format::Markdown,
+        Format::Xml,
+        Format::Json,
+        Format::JsonLines,
+    ] {
+        let source = TempDir::new().unwrap();
+        fs::create_dir(source.path().join("src")).unwrap();
+        fs::write(
+            source.path().join("src/main.rs"),
+            "fn main() {\n    a[b[0]]>c;\n}\n",
+        )
+        .unwrap();
+        fs::write(source.path().join("lib.rs"), "/// ```\npub fn lib() {}\n").unwrap();
+        fs::write(source.path().join("empty.rs"), "").unwrap();
+        fs::write(source.path().join("tail.rs"), "fn tail() {}\r").unwrap();
+
+        let output = TempDir::new().unwrap();
+        let pack_path = output.path().join("pack");
+        pack(source.path(), &pack_path, format);
+
+        let destination = output.path().join("out");
+        let written = Unpacker::new(&destination)
+            .unpack_file(&pack_path)
+            .expect("Failed to unpack");
+        assert_eq!(written.len(), 4, "{:?}", format);
+
+        for name in ["src/main.rs", "lib.rs", "empty.rs", "tail.rs"] {
+            assert_eq!(
+                fs::read_to_string(destination.join(name)).unwrap(),
+                fs::read_to_string(source.path().join(name)).unwrap(),
+                "{} in {:?}",
+                name,
+                format
+            );
+        }
+    }
+}
+
+#[test]
+fn test_unpack_rejects_path_traversal() {
+    let destination = TempDir::new().unwrap();
+    let pack = "\n--- ok.rs ---\nfine\n\n--- ../escape.rs ---\nevil\n";
+
+    assert!(Unpacker::new(destination.path()).unpack(pack).is_err());
+    // Nothing is written when any path is unsafe
+    assert!(!destination.path().join("ok.rs").exists());
+    assert!(!destination
+        .path()
+        .parent()
+        .unwrap()
+        .join("escape.rs")
+        .exists());
+
+    let absolute = "\n--- /etc/escape.rs ---\nevil\n";
+    assert!(Unpacker::new(destination.path()).unpack(absolute).is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_unpack_does_not_follow_symlinks() {
+    use std::os::unix::fs::symlink;
+
+    let outside = TempDir::new().unwrap();
+    let victim = outside.path().join("victim.txt");
+    fs::write(&victim, "precious\n").unwrap();
+    let destination = TempDir::new().unwrap();
+    symlink(outside.path(), destination.path().join("link")).unwrap();
+    symlink(&victim, destination.path().join("a.rs")).unwrap();
+
+    for pack in [
+        "\n--- ok.rs ---\nfine\n\n--- link/x.rs ---\nevil\n",
+        "\n--- ok.rs ---\nfine\n\n--- a.rs ---\nevil\n",
+    ] {
+        assert!(matches!(
+            Unpacker::new(destination.path()).unpack(pack),
+            Err(Error::UnsafePath(_))
+        ));
+    }
+    assert!(!destination.path().join("ok.rs").exists());
+    assert_eq!(
+        fs::read_dir(outside.path()).unwrap().count(),
+        1,
+        "nothing is created outside the destination"
+    );
+    assert_eq!(fs::read_to_string(&victim).unwrap(), "precious\n");
+}
+
+#[test]
+fn test_parse_legacy_text_pack() {
+    let pack = "This is a .txt file representing an entire directory's contents.\n\
+                Each file is separated by a line with its path.\n\n\
+                \n--- a.rs ---\nfn a() {}\n\n--- ./b/c.rs ---\n\nfn c() {}\n";
+
+    let files = Unpacker::parse(pack).unwrap();
+    assert_eq!(files.len(), 2);
+    assert_eq!(files[0].path, Path::new("a.rs"));
+    assert_eq!(files[0].content, "fn a() {}");
+    assert_eq!(files[1].path, Path::new("./b/c.rs"));
+    assert_eq!(files[1].content, "\nfn c() {}");
+}
+
+#[test]
+fn test_text_pack_round_trips_byte_exactly() {
+    let source = TempDir::new().unwrap();
+    let contents = [
+        ("no_newline.rs", "fn a() {}"),
+        ("empty.rs", ""),
+        ("crlf.rs", "fn b() {}\r\n\r\n"),
+        (
+            "collision.rs",
+            "\n--- other.rs ---\n--codepack-0123456789abcdef0123456789abcdef x.rs\n",
+        ),
+    ];
+    for (name, content) in contents {
+        fs::write(source.path().join(name), content).unwrap();
+    }
+
+    let output = TempDir::new().unwrap();
+    let pack_path = output.path().join("pack.txt");
+    pack(source.path(), &pack_path, Format::Text);
+
+    let files = Unpacker::parse(&fs::read_to_string(&pack_path).unwrap()).unwrap();
+    assert_eq!(files.len(), contents.len());
+    for (name, content) in contents {
+        let file = files.iter().find(|f| f.path == Path::new(name)).unwrap();
+        assert_eq!(file.content, content, "{}", name);
+    }
+}